@@ -0,0 +1,137 @@
+//! Per-frame analysis cost across FFT sizes, plus a check that the hot path
+//! doesn't allocate once the analyzer is warmed up.
+//!
+//! Run with `cargo bench --bench analysis --no-default-features`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use synthwave_lib::audio::analysis::{AudioAnalyzer, StereoAnalyzer};
+use synthwave_lib::audio::types::{AudioConfig, SpectrumLayout, SpectrumScale};
+
+/// Counts every allocation so the bench can report allocations per frame.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const FFT_SIZES: &[usize] = &[1024, 2048, 4096, 8192, 16384];
+
+fn test_signal(len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|i| {
+            let t = i as f32 / 44_100.0;
+            0.5 * (std::f32::consts::TAU * 440.0 * t).sin()
+                + 0.25 * (std::f32::consts::TAU * 3_000.0 * t).sin()
+        })
+        .collect()
+}
+
+fn analyze(c: &mut Criterion) {
+    let mut group = c.benchmark_group("analyze");
+    for &fft_size in FFT_SIZES {
+        let samples = test_signal(fft_size);
+        let mut analyzer = AudioAnalyzer::new(fft_size, 44_100);
+        group.throughput(Throughput::Elements(fft_size as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(fft_size),
+            &samples,
+            |b, samples| {
+                b.iter(|| {
+                    let result = analyzer.analyze(black_box(samples)).unwrap();
+                    black_box(result.flux);
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Every optional feature on, with `layout` for the bands.
+fn full_config(layout: SpectrumLayout) -> AudioConfig {
+    AudioConfig {
+        spectrum_layout: layout,
+        spectrum_scale: SpectrumScale::Decibel,
+        auto_gain: true,
+        mfcc: true,
+        chroma: true,
+        pitch: true,
+        ..AudioConfig::default()
+    }
+}
+
+fn count_allocations(frames: usize, mut frame: impl FnMut()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..frames {
+        frame();
+    }
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn allocations(_: &mut Criterion) {
+    const FRAMES: usize = 1_000;
+    let configs = [
+        ("default", AudioConfig::default()),
+        ("log+all", full_config(SpectrumLayout::Log)),
+        ("mel+all", full_config(SpectrumLayout::Mel)),
+        ("octave+all", full_config(SpectrumLayout::ThirdOctave)),
+    ];
+    for &fft_size in FFT_SIZES {
+        let samples = test_signal(fft_size);
+        let right: Vec<f32> = samples.iter().map(|s| -0.5 * s).collect();
+
+        for (name, config) in &configs {
+            let mut analyzer = AudioAnalyzer::new(fft_size, 44_100);
+            analyzer.configure(config, 60.0);
+            // The first call may size the waveform buffer
+            analyzer.analyze(&samples);
+
+            let allocs = count_allocations(FRAMES, || {
+                black_box(analyzer.analyze(black_box(&samples)));
+            });
+            println!(
+                "analyze/{name}/{fft_size}: {allocs} allocations over {FRAMES} frames \
+                 ({:.3} per frame)",
+                allocs as f64 / FRAMES as f64
+            );
+            assert_eq!(
+                allocs, 0,
+                "analyze allocated on the hot path with {name} at fft size {fft_size}"
+            );
+        }
+
+        let mut stereo = StereoAnalyzer::new(fft_size, 44_100);
+        stereo.analyze(&samples, &right);
+        let allocs = count_allocations(FRAMES, || {
+            black_box(stereo.analyze(black_box(&samples), black_box(&right)));
+        });
+        println!("stereo/{fft_size}: {allocs} allocations over {FRAMES} frames");
+        assert_eq!(
+            allocs, 0,
+            "stereo analysis allocated on the hot path at fft size {fft_size}"
+        );
+    }
+}
+
+criterion_group!(benches, allocations, analyze);
+criterion_main!(benches);
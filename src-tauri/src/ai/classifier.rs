@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 
 use super::ollama::OllamaClient;
+use crate::audio::key::PITCH_CLASSES;
+use crate::audio::offline::FeatureSummary;
 use crate::error::AppError;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,29 +24,54 @@ impl AudioClassifier {
         }
     }
 
-    pub async fn classify(
-        &self,
-        avg_rms: f32,
-        avg_centroid: f32,
-        avg_flux: f32,
-        avg_zcr: f32,
-        bpm: f32,
-        beat_regularity: f32,
-    ) -> Result<Classification, AppError> {
-        let prompt = format!(
-            r#"Analyze these audio features and respond with ONLY a JSON object:
-- RMS (volume): {avg_rms:.3}
-- Spectral Centroid (brightness): {avg_centroid:.3}
+    pub async fn classify(&self, summary: &FeatureSummary) -> Result<Classification, AppError> {
+        let response = self.client.generate(&Self::prompt(summary)).await?;
+        self.parse_response(&response)
+    }
+
+    fn prompt(summary: &FeatureSummary) -> String {
+        let FeatureSummary {
+            avg_rms,
+            avg_centroid,
+            avg_flux,
+            avg_zcr,
+            bpm,
+            beat_regularity,
+            mfcc,
+            chroma,
+            key,
+        } = summary;
+
+        let mut features = format!(
+            r#"- RMS (volume): {avg_rms:.3}
+- Spectral Centroid (brightness): {avg_centroid:.0} Hz
 - Spectral Flux (change): {avg_flux:.3}
 - Zero Crossing Rate: {avg_zcr:.3}
 - BPM: {bpm:.0}
-- Beat Regularity: {beat_regularity:.2}
-
-Respond with exactly: {{"genre": "<genre>", "mood": "<mood>", "energy": "<low|medium|high>"}}"#
+- Beat Regularity: {beat_regularity:.2}"#
         );
+        if !mfcc.is_empty() {
+            let values: Vec<String> = mfcc.iter().map(|c| format!("{c:.1}")).collect();
+            features += &format!("\n- MFCC (timbre): [{}]", values.join(", "));
+        }
+        if let Some(dominant) = dominant_pitch_class(chroma) {
+            let values: Vec<String> = chroma.iter().map(|c| format!("{c:.2}")).collect();
+            features += &format!(
+                "\n- Chroma (C to B): [{}], strongest {dominant}",
+                values.join(", ")
+            );
+        }
 
-        let response = self.client.generate(&prompt).await?;
-        self.parse_response(&response)
+        if let Some(key) = key {
+            features += &format!("\n- Key: {key} (confidence {:.2})", key.confidence);
+        }
+
+        format!(
+            r#"Analyze these audio features and respond with ONLY a JSON object:
+{features}
+
+Respond with exactly: {{"genre": "<genre>", "mood": "<mood>", "energy": "<low|medium|high>"}}"#
+        )
     }
 
     fn parse_response(&self, response: &str) -> Result<Classification, AppError> {
@@ -65,9 +92,19 @@ Respond with exactly: {{"genre": "<genre>", "mood": "<mood>", "energy": "<low|me
     }
 }
 
+fn dominant_pitch_class(chroma: &[f32]) -> Option<&'static str> {
+    chroma
+        .iter()
+        .take(PITCH_CLASSES.len())
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(i, _)| PITCH_CLASSES[i])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::audio::key::{KeyEstimate, Mode};
 
     #[test]
     fn test_parse_valid_response() {
@@ -87,4 +124,30 @@ mod tests {
         let response = "I don't know what to say";
         assert!(classifier.parse_response(response).is_err());
     }
+
+    #[test]
+    fn test_prompt_lists_optional_features_only_when_present() {
+        let mut summary = FeatureSummary {
+            avg_centroid: 1500.0,
+            bpm: 128.0,
+            ..Default::default()
+        };
+        let prompt = AudioClassifier::prompt(&summary);
+        assert!(prompt.contains("1500 Hz"));
+        assert!(!prompt.contains("MFCC") && !prompt.contains("Chroma"));
+
+        summary.mfcc = vec![-200.0, 12.5];
+        summary.chroma = vec![0.1, 0.0, 0.2, 0.0, 0.3, 0.1, 0.0, 0.4, 0.0, 1.0, 0.0, 0.2];
+        let prompt = AudioClassifier::prompt(&summary);
+        assert!(prompt.contains("[-200.0, 12.5]"));
+        assert!(prompt.contains("strongest A"));
+        assert!(!prompt.contains("Key:"));
+
+        summary.key = Some(KeyEstimate {
+            tonic: 9,
+            mode: Mode::Minor,
+            confidence: 0.8,
+        });
+        assert!(AudioClassifier::prompt(&summary).contains("Key: A minor (confidence 0.80)"));
+    }
 }
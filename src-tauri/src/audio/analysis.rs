@@ -1,97 +1,217 @@
-use rustfft::{num_complex::Complex, FftPlanner};
+mod bands;
+mod chroma;
+mod envelope;
+mod fft;
+mod loudness;
+mod mfcc;
+mod pitch;
+mod scale;
+mod stereo;
 
+use serde::{Deserialize, Serialize};
+
+pub use bands::{BandMapper, SpectrumBand};
+pub use chroma::{Chroma, CHROMA_BINS};
+pub use envelope::EnvelopeFollower;
+pub use loudness::{Loudness, LoudnessMeter};
+pub use mfcc::{Mfcc, MFCC_COEFFICIENTS};
+pub use pitch::{Pitch, PitchTracker};
+pub use scale::SpectrumScaler;
+pub use stereo::{StereoAnalyzer, StereoResult};
+
+use fft::MagnitudeFft;
+use super::types::{AudioConfig, SpectrumLayout, SpectrumScale};
+
+const WAVEFORM_POINTS: usize = 1024;
+/// Share of spectral energy below the rolloff frequency.
+const ROLLOFF_FRACTION: f32 = 0.85;
+
+/// Upper edges in Hz of the named energy bands, from sub to brilliance.
+const ENERGY_BAND_EDGES: [f32; 7] = [60.0, 250.0, 500.0, 2000.0, 4000.0, 6000.0, 20000.0];
+/// Lower edge of the sub band.
+const ENERGY_BAND_START: f32 = 20.0;
+
+/// Per-frame spectral analysis. The FFT plan, scratch space and output
+/// buffers are all allocated up front, so `analyze` doesn't allocate.
 pub struct AudioAnalyzer {
     fft_size: usize,
-    planner: FftPlanner<f32>,
-    window: Vec<f32>,
+    fft: MagnitudeFft,
+    /// Absolute magnitudes, before per-frame normalization.
+    magnitudes: Vec<f32>,
     prev_spectrum: Vec<f32>,
+    band_mapper: Option<BandMapper>,
+    scaler: SpectrumScaler,
+    envelope: EnvelopeFollower,
+    mfcc: Option<Mfcc>,
+    chroma: Option<Chroma>,
+    pitch: Option<PitchTracker>,
+    result: AnalysisResult,
 }
 
 impl AudioAnalyzer {
-    pub fn new(fft_size: usize) -> Self {
-        let window = apodize::hanning_iter(fft_size)
-            .map(|v| v as f32)
-            .collect();
+    pub fn new(fft_size: usize, sample_rate: u32) -> Self {
         let spectrum_len = fft_size / 2;
         Self {
             fft_size,
-            planner: FftPlanner::new(),
-            window,
+            fft: MagnitudeFft::new(fft_size),
+            magnitudes: vec![0.0; spectrum_len],
             prev_spectrum: vec![0.0; spectrum_len],
+            band_mapper: None,
+            scaler: SpectrumScaler::new(&AudioConfig::default(), 60.0),
+            envelope: EnvelopeFollower::new(&AudioConfig::default(), 60.0, spectrum_len),
+            mfcc: None,
+            chroma: None,
+            pitch: None,
+            result: AnalysisResult {
+                spectrum: vec![0.0; spectrum_len],
+                display: vec![0.0; spectrum_len],
+                smoothed: vec![0.0; spectrum_len],
+                peaks: vec![0.0; spectrum_len],
+                waveform: Vec::with_capacity(WAVEFORM_POINTS),
+                rms: 0.0,
+                centroid: 0.0,
+                rolloff: 0.0,
+                flatness: 0.0,
+                crest: 0.0,
+                bandwidth: 0.0,
+                slope: 0.0,
+                band_energy: BandEnergies::default(),
+                flux: 0.0,
+                zcr: 0.0,
+                mfcc: Vec::new(),
+                chroma: Vec::new(),
+                f0: 0.0,
+                pitch_confidence: 0.0,
+                sample_rate,
+                bin_hz: sample_rate as f32 / fft_size as f32,
+            },
         }
     }
 
-    pub fn analyze(&mut self, samples: &[f32]) -> Option<AnalysisResult> {
+    pub fn sample_rate(&self) -> u32 {
+        self.result.sample_rate
+    }
+
+    /// Width of one spectrum bin in Hz; bin `i` is centred on `i * bin_hz()`.
+    pub fn bin_hz(&self) -> f32 {
+        self.result.bin_hz
+    }
+
+    /// Applies the display settings from `config` (band layout, scaling
+    /// and envelopes) and turns the optional features on or off.
+    /// `frame_rate` is how often `analyze` will be called, which
+    /// time-based settings are converted with. Cheap compared to a new
+    /// analyzer: the FFT plan is kept.
+    pub fn configure(&mut self, config: &AudioConfig, frame_rate: f32) {
+        self.set_band_layout(config.spectrum_layout, config.band_count);
+        self.scaler = SpectrumScaler::new(config, frame_rate);
+        let len = self.result.display.len();
+        self.envelope = EnvelopeFollower::new(config, frame_rate, len);
+        self.result.smoothed = vec![0.0; len];
+        self.result.peaks = vec![0.0; len];
+
+        let (spectrum_len, bin_hz) = (self.fft_size / 2, self.result.bin_hz);
+        self.mfcc = config.mfcc.then(|| Mfcc::new(spectrum_len, bin_hz));
+        self.result.mfcc = vec![0.0; if config.mfcc { MFCC_COEFFICIENTS } else { 0 }];
+        self.chroma = config.chroma.then(|| Chroma::new(spectrum_len, bin_hz));
+        self.result.chroma = vec![0.0; if config.chroma { CHROMA_BINS } else { 0 }];
+        self.pitch = config
+            .pitch
+            .then(|| PitchTracker::new(self.fft_size, self.result.sample_rate));
+    }
+
+    fn set_band_layout(&mut self, layout: SpectrumLayout, band_count: usize) {
+        let spectrum_len = self.fft_size / 2;
+        self.band_mapper = BandMapper::new(layout, band_count, spectrum_len, self.result.bin_hz);
+        let len = self
+            .band_mapper
+            .as_ref()
+            .map_or(spectrum_len, |m| m.bands().len());
+        self.result.display = vec![0.0; len];
+    }
+
+    /// Frequency ranges of the current bands; empty for the linear layout.
+    pub fn bands(&self) -> &[SpectrumBand] {
+        self.band_mapper.as_ref().map_or(&[], |m| m.bands())
+    }
+
+    /// Analyzes the first `fft_size` samples (RMS, ZCR and the waveform use
+    /// all of `samples`). The result borrows the analyzer's buffers and is
+    /// overwritten by the next call.
+    pub fn analyze(&mut self, samples: &[f32]) -> Option<&AnalysisResult> {
         let n = self.fft_size;
         if samples.len() < n {
             return None;
         }
 
-        // Apply window and convert to complex
-        let mut buffer: Vec<Complex<f32>> = samples[..n]
-            .iter()
-            .zip(self.window.iter())
-            .map(|(&s, &w)| Complex::new(s * w, 0.0))
-            .collect();
-
-        // Run FFT
-        let fft = self.planner.plan_fft_forward(n);
-        fft.process(&mut buffer);
-
-        // Compute magnitude spectrum (only positive frequencies)
-        let spectrum_len = n / 2;
-        let norm = 2.0 / n as f32;
-        let mut spectrum: Vec<f32> = buffer[..spectrum_len]
-            .iter()
-            .map(|c| c.norm() * norm)
-            .collect();
+        self.fft.process(samples, &mut self.magnitudes)?;
+        let result = &mut self.result;
 
         // Normalize spectrum to 0..1 range
-        let max_val = spectrum.iter().cloned().fold(0.0f32, f32::max);
-        if max_val > 0.0 {
-            for s in &mut spectrum {
-                *s /= max_val;
-            }
+        let max_val = self.magnitudes.iter().cloned().fold(0.0f32, f32::max);
+        let inv_max = if max_val > 0.0 { 1.0 / max_val } else { 0.0 };
+        for (s, &m) in result.spectrum.iter_mut().zip(&self.magnitudes) {
+            *s = m * inv_max;
         }
 
-        // Waveform: downsample to 1024 points
-        let waveform = Self::downsample(samples, 1024);
+        // Display spectrum: normalized or absolute, then banded and scaled
+        let source = match self.scaler.scale() {
+            SpectrumScale::Normalized => &result.spectrum,
+            SpectrumScale::Decibel => &self.magnitudes,
+        };
+        match &self.band_mapper {
+            Some(mapper) => mapper.map(source, &mut result.display),
+            None => result.display.copy_from_slice(source),
+        }
+        self.scaler.apply(&mut result.display);
 
-        // RMS
-        let rms = Self::compute_rms(samples);
+        self.envelope.process(&result.display);
+        result.smoothed.copy_from_slice(self.envelope.smoothed());
+        result.peaks.copy_from_slice(self.envelope.peaks());
 
-        // Spectral centroid
-        let centroid = Self::compute_centroid(&spectrum);
+        // Waveform: downsample to 1024 points
+        Self::downsample(samples, WAVEFORM_POINTS, &mut result.waveform);
 
-        // Spectral flux
-        let flux = Self::compute_flux(&spectrum, &self.prev_spectrum);
+        result.rms = Self::compute_rms(samples);
+        let bin_hz = result.bin_hz;
+        result.centroid = Self::compute_centroid(&result.spectrum, bin_hz);
+        result.rolloff = Self::compute_rolloff(&result.spectrum, bin_hz, ROLLOFF_FRACTION);
+        result.flatness = Self::compute_flatness(&result.spectrum);
+        result.crest = Self::compute_crest(&result.spectrum);
+        result.bandwidth = Self::compute_bandwidth(&result.spectrum, bin_hz, result.centroid);
+        result.slope = Self::compute_slope(&result.spectrum, bin_hz);
+        result.band_energy = Self::compute_band_energy(&result.spectrum, bin_hz);
+        result.flux = Self::compute_flux(&result.spectrum, &self.prev_spectrum);
+        result.zcr = Self::compute_zcr(samples);
 
-        // Zero-crossing rate
-        let zcr = Self::compute_zcr(samples);
+        if let Some(mfcc) = &mut self.mfcc {
+            mfcc.compute(&self.magnitudes, &mut result.mfcc);
+        }
+        if let Some(chroma) = &self.chroma {
+            chroma.compute(&self.magnitudes, &mut result.chroma);
+        }
+        (result.f0, result.pitch_confidence) = self
+            .pitch
+            .as_mut()
+            .and_then(|p| p.detect(&samples[..n]))
+            .unwrap_or((0.0, 0.0));
 
-        self.prev_spectrum.copy_from_slice(&spectrum);
+        self.prev_spectrum.copy_from_slice(&result.spectrum);
 
-        Some(AnalysisResult {
-            spectrum,
-            waveform,
-            rms,
-            centroid,
-            flux,
-            zcr,
-        })
+        Some(&self.result)
     }
 
-    fn downsample(samples: &[f32], target_len: usize) -> Vec<f32> {
+    fn downsample(samples: &[f32], target_len: usize, out: &mut Vec<f32>) {
+        out.clear();
         if samples.len() <= target_len {
-            return samples.to_vec();
+            out.extend_from_slice(samples);
+            return;
         }
         let step = samples.len() as f32 / target_len as f32;
-        (0..target_len)
-            .map(|i| {
-                let idx = (i as f32 * step) as usize;
-                samples[idx.min(samples.len() - 1)]
-            })
-            .collect()
+        out.extend((0..target_len).map(|i| {
+            let idx = (i as f32 * step) as usize;
+            samples[idx.min(samples.len() - 1)]
+        }));
     }
 
     fn compute_rms(samples: &[f32]) -> f32 {
@@ -99,7 +219,8 @@ impl AudioAnalyzer {
         (sum / samples.len() as f32).sqrt()
     }
 
-    fn compute_centroid(spectrum: &[f32]) -> f32 {
+    /// Magnitude-weighted mean frequency in Hz.
+    fn compute_centroid(spectrum: &[f32], bin_hz: f32) -> f32 {
         let total_energy: f32 = spectrum.iter().sum();
         if total_energy < 1e-10 {
             return 0.0;
@@ -109,7 +230,106 @@ impl AudioAnalyzer {
             .enumerate()
             .map(|(i, &s)| i as f32 * s)
             .sum();
-        weighted / total_energy / spectrum.len() as f32
+        weighted / total_energy * bin_hz
+    }
+
+    /// Frequency in Hz below which `fraction` of the spectral energy lies.
+    fn compute_rolloff(spectrum: &[f32], bin_hz: f32, fraction: f32) -> f32 {
+        let total_energy: f32 = spectrum.iter().map(|s| s * s).sum();
+        if total_energy < 1e-10 {
+            return 0.0;
+        }
+        let target = total_energy * fraction;
+        let mut cumulative = 0.0;
+        for (i, &s) in spectrum.iter().enumerate() {
+            cumulative += s * s;
+            if cumulative >= target {
+                return i as f32 * bin_hz;
+            }
+        }
+        (spectrum.len() - 1) as f32 * bin_hz
+    }
+
+    /// Geometric over arithmetic mean of the power spectrum: near 1 for
+    /// noise, near 0 for tones.
+    fn compute_flatness(spectrum: &[f32]) -> f32 {
+        let n = spectrum.len() as f32;
+        let arithmetic = spectrum.iter().map(|s| s * s).sum::<f32>() / n;
+        if arithmetic < 1e-10 {
+            return 0.0;
+        }
+        let log_mean = spectrum
+            .iter()
+            .map(|s| (s * s).max(1e-10).ln())
+            .sum::<f32>()
+            / n;
+        log_mean.exp() / arithmetic
+    }
+
+    /// Peak over mean magnitude: high for tonal spectra.
+    fn compute_crest(spectrum: &[f32]) -> f32 {
+        let mean = spectrum.iter().sum::<f32>() / spectrum.len() as f32;
+        if mean < 1e-10 {
+            return 0.0;
+        }
+        spectrum.iter().cloned().fold(0.0f32, f32::max) / mean
+    }
+
+    /// Magnitude-weighted spread around the centroid, in Hz.
+    fn compute_bandwidth(spectrum: &[f32], bin_hz: f32, centroid: f32) -> f32 {
+        let total: f32 = spectrum.iter().sum();
+        if total < 1e-10 {
+            return 0.0;
+        }
+        let variance: f32 = spectrum
+            .iter()
+            .enumerate()
+            .map(|(i, &s)| {
+                let d = i as f32 * bin_hz - centroid;
+                s * d * d
+            })
+            .sum::<f32>()
+            / total;
+        variance.sqrt()
+    }
+
+    /// Least-squares slope of magnitude against frequency, per kHz.
+    /// Negative when energy falls off towards the highs.
+    fn compute_slope(spectrum: &[f32], bin_hz: f32) -> f32 {
+        let n = spectrum.len() as f32;
+        let khz = |i: usize| i as f32 * bin_hz / 1000.0;
+        let mean_f = (0..spectrum.len()).map(khz).sum::<f32>() / n;
+        let mean_m = spectrum.iter().sum::<f32>() / n;
+        let (mut cov, mut var) = (0.0, 0.0);
+        for (i, &m) in spectrum.iter().enumerate() {
+            let df = khz(i) - mean_f;
+            cov += df * (m - mean_m);
+            var += df * df;
+        }
+        if var < 1e-10 {
+            0.0
+        } else {
+            cov / var
+        }
+    }
+
+    /// Share of spectral energy in each named band.
+    fn compute_band_energy(spectrum: &[f32], bin_hz: f32) -> BandEnergies {
+        let total: f32 = spectrum.iter().map(|s| s * s).sum();
+        let mut shares = [0.0f32; 7];
+        if total < 1e-10 {
+            return BandEnergies::from(shares);
+        }
+        for (i, &s) in spectrum.iter().enumerate() {
+            let hz = i as f32 * bin_hz;
+            if hz < ENERGY_BAND_START {
+                continue;
+            }
+            if let Some(band) = ENERGY_BAND_EDGES.iter().position(|&edge| hz < edge) {
+                shares[band] += s * s / total;
+            }
+        }
+        BandEnergies::from(shares)
     }
 
     fn compute_flux(current: &[f32], previous: &[f32]) -> f32 {
@@ -136,13 +356,75 @@ impl AudioAnalyzer {
     }
 }
 
+/// Share of spectral energy (0..1) in conventional mixing bands.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandEnergies {
+    /// 20-60 Hz
+    pub sub: f32,
+    /// 60-250 Hz
+    pub bass: f32,
+    /// 250-500 Hz
+    pub low_mid: f32,
+    /// 500 Hz-2 kHz
+    pub mid: f32,
+    /// 2-4 kHz
+    pub high_mid: f32,
+    /// 4-6 kHz
+    pub presence: f32,
+    /// 6-20 kHz
+    pub brilliance: f32,
+}
+
+impl From<[f32; 7]> for BandEnergies {
+    fn from(b: [f32; 7]) -> Self {
+        Self {
+            sub: b[0],
+            bass: b[1],
+            low_mid: b[2],
+            mid: b[3],
+            high_mid: b[4],
+            presence: b[5],
+            brilliance: b[6],
+        }
+    }
+}
+
 pub struct AnalysisResult {
+    /// Linear magnitude spectrum, `fft_size / 2` bins.
     pub spectrum: Vec<f32>,
+    /// What the frontend draws: `spectrum` or absolute magnitudes per
+    /// the configured scale, mapped onto the configured bands, in 0..1.
+    pub display: Vec<f32>,
+    /// `display` through the attack/release envelope.
+    pub smoothed: Vec<f32>,
+    /// Peak-hold markers over `display`.
+    pub peaks: Vec<f32>,
     pub waveform: Vec<f32>,
     pub rms: f32,
+    /// Spectral centroid in Hz.
     pub centroid: f32,
+    /// Spectral rolloff in Hz.
+    pub rolloff: f32,
+    pub flatness: f32,
+    pub crest: f32,
+    /// Spectral bandwidth in Hz.
+    pub bandwidth: f32,
+    /// Spectral slope per kHz.
+    pub slope: f32,
+    pub band_energy: BandEnergies,
     pub flux: f32,
     pub zcr: f32,
+    /// Mel-frequency cepstral coefficients; empty unless enabled.
+    pub mfcc: Vec<f32>,
+    /// Energy per pitch class, C first, loudest at 1; empty unless enabled.
+    pub chroma: Vec<f32>,
+    /// Fundamental in Hz; 0 when unvoiced or pitch tracking is off.
+    pub f0: f32,
+    pub pitch_confidence: f32,
+    pub sample_rate: u32,
+    /// Width of one spectrum bin in Hz.
+    pub bin_hz: f32,
 }
 
 #[cfg(test)]
@@ -160,7 +442,7 @@ mod tests {
             .map(|i| (2.0 * PI * freq * i as f32 / sample_rate).sin())
             .collect();
 
-        let mut analyzer = AudioAnalyzer::new(fft_size);
+        let mut analyzer = AudioAnalyzer::new(fft_size, 44100);
         let result = analyzer.analyze(&samples).expect("analyze should return result for valid input");
 
         // Find peak bin
@@ -186,7 +468,7 @@ mod tests {
     fn test_fft_silence() {
         let fft_size = 2048;
         let samples = vec![0.0f32; fft_size];
-        let mut analyzer = AudioAnalyzer::new(fft_size);
+        let mut analyzer = AudioAnalyzer::new(fft_size, 44100);
         let result = analyzer.analyze(&samples).expect("analyze should return result for valid input");
 
         let max_val = result.spectrum.iter().cloned().fold(0.0f32, f32::max);
@@ -205,13 +487,198 @@ mod tests {
     fn test_centroid_single_freq() {
         let mut spectrum = vec![0.0f32; 512];
         spectrum[100] = 1.0;
-        let centroid = AudioAnalyzer::compute_centroid(&spectrum);
-        let expected = 100.0 / 512.0;
+        let centroid = AudioAnalyzer::compute_centroid(&spectrum, 43.0);
+        let expected = 100.0 * 43.0;
         assert!(
-            (centroid - expected).abs() < 0.01,
+            (centroid - expected).abs() < 1.0,
             "Centroid {}, expected {}",
             centroid,
             expected
         );
     }
+
+    #[test]
+    fn test_analyze_reuses_buffers() {
+        let fft_size = 4096;
+        let samples: Vec<f32> = (0..fft_size).map(|i| (i as f32 * 0.05).sin()).collect();
+        let mut analyzer = AudioAnalyzer::new(fft_size, 44100);
+
+        let first = analyzer.analyze(&samples).unwrap();
+        let (spectrum_ptr, waveform_ptr) = (first.spectrum.as_ptr(), first.waveform.as_ptr());
+        assert_eq!(first.spectrum.len(), fft_size / 2);
+        assert_eq!(first.waveform.len(), 1024);
+
+        let second = analyzer.analyze(&samples).unwrap();
+        assert_eq!(second.spectrum.as_ptr(), spectrum_ptr);
+        assert_eq!(second.waveform.as_ptr(), waveform_ptr);
+        // Same input twice: no spectral change
+        assert!(second.flux < 1e-6);
+    }
+
+    #[test]
+    fn test_features_in_hz_match_across_sample_rates() {
+        let fft_size = 4096;
+        let freq = 1000.0;
+        let mut centroids = Vec::new();
+        for sample_rate in [44100u32, 48000] {
+            let samples: Vec<f32> = (0..fft_size)
+                .map(|i| (2.0 * PI * freq * i as f32 / sample_rate as f32).sin())
+                .collect();
+            let mut analyzer = AudioAnalyzer::new(fft_size, sample_rate);
+            let result = analyzer.analyze(&samples).unwrap();
+            let bin_hz = result.bin_hz;
+            assert!(
+                (result.centroid - freq).abs() < 3.0 * bin_hz,
+                "Centroid {} Hz at {} Hz, expected ~{}",
+                result.centroid,
+                sample_rate,
+                freq
+            );
+            assert!((result.rolloff - freq).abs() < 3.0 * bin_hz);
+            centroids.push(result.centroid);
+        }
+        assert!((centroids[0] - centroids[1]).abs() < 20.0);
+    }
+
+    #[test]
+    fn test_decibel_scale_keeps_quiet_signal_quiet() {
+        let fft_size = 2048;
+        let sample_rate = 48000;
+        // Bin-centred sine at -40 dBFS
+        let freq = 64.0 * sample_rate as f32 / fft_size as f32;
+        let samples: Vec<f32> = (0..fft_size)
+            .map(|i| 0.01 * (2.0 * PI * freq * i as f32 / sample_rate as f32).sin())
+            .collect();
+        let peak = |analyzer: &mut AudioAnalyzer| {
+            let result = analyzer.analyze(&samples).unwrap();
+            result.display.iter().cloned().fold(0.0f32, f32::max)
+        };
+
+        let mut normalized = AudioAnalyzer::new(fft_size, sample_rate);
+        assert!((peak(&mut normalized) - 1.0).abs() < 1e-4);
+
+        let config = AudioConfig {
+            spectrum_scale: SpectrumScale::Decibel,
+            db_floor: -80.0,
+            db_ceiling: 0.0,
+            ..Default::default()
+        };
+        let mut decibel = AudioAnalyzer::new(fft_size, sample_rate);
+        decibel.configure(&config, 60.0);
+        let level = peak(&mut decibel);
+        assert!((level - 0.5).abs() < 0.01, "-40 dBFS shown at {level}");
+    }
+
+    fn sine(freq: f32, sample_rate: u32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * PI * freq * i as f32 / sample_rate as f32).sin())
+            .collect()
+    }
+
+    fn white_noise(len: usize) -> Vec<f32> {
+        let mut rng = fastrand::Rng::with_seed(7);
+        (0..len).map(|_| rng.f32() * 2.0 - 1.0).collect()
+    }
+
+    fn analyze_once(samples: &[f32], sample_rate: u32) -> AnalysisResult {
+        let mut analyzer = AudioAnalyzer::new(samples.len(), sample_rate);
+        analyzer.analyze(samples).unwrap();
+        analyzer.result
+    }
+
+    #[test]
+    fn test_flatness_and_crest_separate_tone_from_noise() {
+        let tone = analyze_once(&sine(1000.0, 48000, 4096), 48000);
+        let noise = analyze_once(&white_noise(4096), 48000);
+
+        assert!(tone.flatness < 0.05, "tone flatness {}", tone.flatness);
+        assert!(noise.flatness > 0.3, "noise flatness {}", noise.flatness);
+        assert!(
+            tone.crest > 10.0 * noise.crest,
+            "tone crest {} vs noise {}",
+            tone.crest,
+            noise.crest
+        );
+    }
+
+    #[test]
+    fn test_bandwidth_narrow_for_tone_wide_for_noise() {
+        let tone = analyze_once(&sine(1000.0, 48000, 4096), 48000);
+        let noise = analyze_once(&white_noise(4096), 48000);
+
+        assert!(tone.bandwidth < 200.0, "tone bandwidth {} Hz", tone.bandwidth);
+        // Flat spectrum over 0..24 kHz: 24k / sqrt(12) ≈ 6.9 kHz
+        assert!(
+            (noise.bandwidth - 6900.0).abs() < 700.0,
+            "noise bandwidth {} Hz",
+            noise.bandwidth
+        );
+    }
+
+    #[test]
+    fn test_slope_follows_spectral_tilt() {
+        let noise = white_noise(4096);
+        // One-pole lowpass tilts energy down, first difference tilts it up
+        let mut prev = 0.0;
+        let dark: Vec<f32> = noise
+            .iter()
+            .map(|&x| {
+                prev = x + 0.9 * prev;
+                prev
+            })
+            .collect();
+        let bright: Vec<f32> = noise.windows(2).map(|w| w[1] - w[0]).chain([0.0]).collect();
+
+        let dark = analyze_once(&dark, 48000);
+        let bright = analyze_once(&bright, 48000);
+        assert!(dark.slope < 0.0, "dark slope {}", dark.slope);
+        assert!(bright.slope > 0.0, "bright slope {}", bright.slope);
+    }
+
+    #[test]
+    fn test_band_energy_puts_tones_in_their_band() {
+        type Band = fn(&BandEnergies) -> f32;
+        let sample_rate = 48000;
+        let cases: [(f32, Band); 7] = [
+            (40.0, |b| b.sub),
+            (120.0, |b| b.bass),
+            (350.0, |b| b.low_mid),
+            (1000.0, |b| b.mid),
+            (3000.0, |b| b.high_mid),
+            (5000.0, |b| b.presence),
+            (10000.0, |b| b.brilliance),
+        ];
+        for (freq, band) in cases {
+            let result = analyze_once(&sine(freq, sample_rate, 8192), sample_rate);
+            let share = band(&result.band_energy);
+            assert!(share > 0.95, "{freq} Hz: {:?}", result.band_energy);
+        }
+
+        let silence = analyze_once(&vec![0.0; 4096], sample_rate);
+        assert_eq!(silence.band_energy, BandEnergies::default());
+    }
+
+    #[test]
+    fn test_optional_features_only_when_enabled() {
+        let samples = sine(440.0, 48000, 4096);
+        let mut analyzer = AudioAnalyzer::new(4096, 48000);
+        let result = analyzer.analyze(&samples).unwrap();
+        assert!(result.mfcc.is_empty() && result.chroma.is_empty());
+
+        let config = AudioConfig {
+            mfcc: true,
+            chroma: true,
+            ..Default::default()
+        };
+        analyzer.configure(&config, 60.0);
+        let result = analyzer.analyze(&samples).unwrap();
+        assert_eq!(result.mfcc.len(), MFCC_COEFFICIENTS);
+        assert_eq!(result.chroma.len(), CHROMA_BINS);
+        assert_eq!(result.chroma[9], 1.0);
+        assert_eq!(result.f0, 0.0);
+
+        analyzer.configure(&AudioConfig { pitch: true, ..config }, 60.0);
+        let result = analyzer.analyze(&samples).unwrap();
+        assert!((result.f0 - 440.0).abs() < 2.0, "{}", result.f0);
+    }
 }
@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+
+use crate::audio::types::SpectrumLayout;
+
+/// Lowest band edge for the log, mel and octave layouts.
+pub(super) const MIN_BAND_HZ: f32 = 20.0;
+/// Highest band edge, capped at Nyquist for low sample rates.
+pub(super) const MAX_BAND_HZ: f32 = 20_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpectrumBand {
+    pub low_hz: f32,
+    pub center_hz: f32,
+    pub high_hz: f32,
+}
+
+/// Maps a linear magnitude spectrum onto perceptually spaced bands.
+///
+/// The bin weights are worked out once, so `map` is a flat weighted sum.
+/// Bands wider than a bin average the bins they cover, weighting the edge
+/// bins by overlap; bands narrower than a bin (the low end of large layouts)
+/// interpolate between the two bins either side of their centre.
+pub struct BandMapper {
+    bands: Vec<SpectrumBand>,
+    /// `(bin, weight)` pairs for every band, back to back.
+    weights: Vec<(usize, f32)>,
+    /// Band `i` uses `weights[offsets[i]..offsets[i + 1]]`.
+    offsets: Vec<usize>,
+}
+
+impl BandMapper {
+    /// Returns `None` for [`SpectrumLayout::Linear`], which needs no mapping.
+    pub fn new(
+        layout: SpectrumLayout,
+        band_count: usize,
+        spectrum_len: usize,
+        bin_hz: f32,
+    ) -> Option<Self> {
+        let max_hz = MAX_BAND_HZ.min(bin_hz * spectrum_len as f32);
+        let bands = match layout {
+            SpectrumLayout::Linear => return None,
+            SpectrumLayout::Log => Self::from_edges(log_edges(band_count, MIN_BAND_HZ, max_hz)),
+            SpectrumLayout::Mel => Self::from_edges(mel_edges(band_count, MIN_BAND_HZ, max_hz)),
+            SpectrumLayout::ThirdOctave => third_octave_bands(MIN_BAND_HZ, max_hz),
+        };
+
+        let mut weights = Vec::new();
+        let mut offsets = vec![0];
+        for band in &bands {
+            Self::band_weights(band, spectrum_len, bin_hz, &mut weights);
+            offsets.push(weights.len());
+        }
+
+        Some(Self {
+            bands,
+            weights,
+            offsets,
+        })
+    }
+
+    fn from_edges(edges: Vec<f32>) -> Vec<SpectrumBand> {
+        edges
+            .windows(2)
+            .map(|e| SpectrumBand {
+                low_hz: e[0],
+                center_hz: (e[0] * e[1]).sqrt(),
+                high_hz: e[1],
+            })
+            .collect()
+    }
+
+    fn band_weights(
+        band: &SpectrumBand,
+        spectrum_len: usize,
+        bin_hz: f32,
+        out: &mut Vec<(usize, f32)>,
+    ) {
+        let last = spectrum_len - 1;
+        // Band edges in fractional bins; bin k covers [k - 0.5, k + 0.5)
+        let a = band.low_hz / bin_hz;
+        let b = band.high_hz / bin_hz;
+
+        if b - a <= 1.0 {
+            let pos = (band.center_hz / bin_hz).min(last as f32);
+            let lo = pos.floor() as usize;
+            let frac = pos - lo as f32;
+            out.push((lo, 1.0 - frac));
+            if frac > 0.0 && lo < last {
+                out.push((lo + 1, frac));
+            }
+            return;
+        }
+
+        let first = (a + 0.5).floor().max(0.0) as usize;
+        let end = ((b + 0.5).ceil() as usize).min(spectrum_len);
+        for k in first..end {
+            let overlap = (b.min(k as f32 + 0.5) - a.max(k as f32 - 0.5)).max(0.0);
+            if overlap > 0.0 {
+                out.push((k, overlap / (b - a)));
+            }
+        }
+    }
+
+    pub fn bands(&self) -> &[SpectrumBand] {
+        &self.bands
+    }
+
+    /// Fills `out` (one value per band) from a linear `spectrum`.
+    pub fn map(&self, spectrum: &[f32], out: &mut [f32]) {
+        for (i, value) in out.iter_mut().enumerate() {
+            *value = self.weights[self.offsets[i]..self.offsets[i + 1]]
+                .iter()
+                .map(|&(bin, w)| spectrum[bin] * w)
+                .sum();
+        }
+    }
+}
+
+fn log_edges(count: usize, min_hz: f32, max_hz: f32) -> Vec<f32> {
+    let ratio = max_hz / min_hz;
+    (0..=count)
+        .map(|i| min_hz * ratio.powf(i as f32 / count as f32))
+        .collect()
+}
+
+fn hz_to_mel(hz: f32) -> f32 {
+    2595.0 * (1.0 + hz / 700.0).log10()
+}
+
+fn mel_to_hz(mel: f32) -> f32 {
+    700.0 * (10f32.powf(mel / 2595.0) - 1.0)
+}
+
+pub(super) fn mel_edges(count: usize, min_hz: f32, max_hz: f32) -> Vec<f32> {
+    let (lo, hi) = (hz_to_mel(min_hz), hz_to_mel(max_hz));
+    (0..=count)
+        .map(|i| mel_to_hz(lo + (hi - lo) * i as f32 / count as f32))
+        .collect()
+}
+
+/// Base-two 1/3-octave bands centred on 1 kHz that overlap
+/// `[min_hz, max_hz]`, with the outer edges clipped to it. Over the full
+/// audible range this is the familiar 31-band layout.
+fn third_octave_bands(min_hz: f32, max_hz: f32) -> Vec<SpectrumBand> {
+    let half = 2f32.powf(1.0 / 6.0);
+    (-30..=30)
+        .map(|k| 1000.0 * 2f32.powf(k as f32 / 3.0))
+        .filter(|&c| c * half > min_hz && c / half < max_hz)
+        .map(|c| SpectrumBand {
+            low_hz: (c / half).max(min_hz),
+            center_hz: c.clamp(min_hz, max_hz),
+            high_hz: (c * half).min(max_hz),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BIN_HZ: f32 = 48000.0 / 4096.0;
+
+    #[test]
+    fn test_layouts_cover_range_in_order() {
+        for layout in [SpectrumLayout::Log, SpectrumLayout::Mel] {
+            let mapper = BandMapper::new(layout, 64, 2048, BIN_HZ).unwrap();
+            assert_eq!(mapper.bands().len(), 64);
+            let bands = mapper.bands();
+            assert!((bands[0].low_hz - MIN_BAND_HZ).abs() < 0.01);
+            assert!((bands[63].high_hz - MAX_BAND_HZ).abs() < 1.0);
+            assert!(bands.windows(2).all(|b| b[1].center_hz > b[0].center_hz));
+        }
+
+        let octave = BandMapper::new(SpectrumLayout::ThirdOctave, 0, 2048, BIN_HZ).unwrap();
+        assert_eq!(octave.bands().len(), 31);
+        assert!(octave
+            .bands()
+            .iter()
+            .any(|b| (b.center_hz - 1000.0).abs() < 0.01));
+
+        assert!(BandMapper::new(SpectrumLayout::Linear, 64, 2048, BIN_HZ).is_none());
+    }
+
+    #[test]
+    fn test_flat_spectrum_maps_to_flat_bands() {
+        // Averaging weights sum to one, so a flat spectrum stays flat even
+        // for the narrow interpolated bands at the bottom
+        let mapper = BandMapper::new(SpectrumLayout::Log, 128, 2048, BIN_HZ).unwrap();
+        let spectrum = vec![0.5f32; 2048];
+        let mut out = vec![0.0; mapper.bands().len()];
+        mapper.map(&spectrum, &mut out);
+        for v in out {
+            assert!((v - 0.5).abs() < 1e-4, "band value {v}");
+        }
+    }
+
+    #[test]
+    fn test_tone_lands_in_matching_band() {
+        let mapper = BandMapper::new(SpectrumLayout::Mel, 40, 2048, BIN_HZ).unwrap();
+        let mut spectrum = vec![0.0f32; 2048];
+        let bin = (1000.0 / BIN_HZ).round() as usize;
+        spectrum[bin] = 1.0;
+
+        let mut out = vec![0.0; mapper.bands().len()];
+        mapper.map(&spectrum, &mut out);
+        let loudest = out
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, _)| i)
+            .unwrap();
+        let band = mapper.bands()[loudest];
+        let tone = bin as f32 * BIN_HZ;
+        assert!(band.low_hz <= tone && tone <= band.high_hz, "{band:?}");
+    }
+}
@@ -0,0 +1,84 @@
+/// Pitch classes per octave, C first.
+pub const CHROMA_BINS: usize = 12;
+/// Range of FFT bins folded into the chroma vector. Below this bins are too
+/// wide to tell semitones apart; above it harmonics dominate.
+const MIN_CHROMA_HZ: f32 = 55.0;
+const MAX_CHROMA_HZ: f32 = 5000.0;
+
+/// 12-bin chroma: spectral energy folded onto pitch classes C..B.
+pub struct Chroma {
+    /// Pitch class of every FFT bin in range.
+    classes: Vec<(usize, usize)>,
+}
+
+impl Chroma {
+    pub fn new(spectrum_len: usize, bin_hz: f32) -> Self {
+        let classes = (1..spectrum_len)
+            .filter_map(|bin| {
+                let hz = bin as f32 * bin_hz;
+                if !(MIN_CHROMA_HZ..=MAX_CHROMA_HZ).contains(&hz) {
+                    return None;
+                }
+                let midi = 69.0 + 12.0 * (hz / 440.0).log2();
+                Some((bin, (midi.round() as i32).rem_euclid(12) as usize))
+            })
+            .collect();
+        Self { classes }
+    }
+
+    /// Fills `out` (`CHROMA_BINS` long) from absolute magnitudes, scaled so
+    /// the strongest pitch class is 1.
+    pub fn compute(&self, magnitudes: &[f32], out: &mut [f32]) {
+        out.fill(0.0);
+        for &(bin, class) in &self.classes {
+            out[class] += magnitudes[bin] * magnitudes[bin];
+        }
+        let max = out.iter().cloned().fold(0.0f32, f32::max);
+        if max > 1e-10 {
+            for c in out.iter_mut() {
+                *c /= max;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BIN_HZ: f32 = 44100.0 / 8192.0;
+
+    fn tones(freqs: &[f32]) -> Vec<f32> {
+        let mut magnitudes = vec![0.0f32; 4096];
+        for &f in freqs {
+            magnitudes[(f / BIN_HZ).round() as usize] = 1.0;
+        }
+        magnitudes
+    }
+
+    fn top(chroma: &[f32], n: usize) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..CHROMA_BINS).collect();
+        order.sort_by(|&a, &b| chroma[b].total_cmp(&chroma[a]));
+        let mut top = order[..n].to_vec();
+        top.sort_unstable();
+        top
+    }
+
+    #[test]
+    fn test_a440_is_pitch_class_a() {
+        let chroma = Chroma::new(4096, BIN_HZ);
+        let mut out = [0.0; CHROMA_BINS];
+        chroma.compute(&tones(&[440.0, 220.0]), &mut out);
+        assert_eq!(top(&out, 1), vec![9]);
+        assert_eq!(out[9], 1.0);
+    }
+
+    #[test]
+    fn test_c_major_triad() {
+        let chroma = Chroma::new(4096, BIN_HZ);
+        let mut out = [0.0; CHROMA_BINS];
+        // C4, E4, G4
+        chroma.compute(&tones(&[261.63, 329.63, 392.0]), &mut out);
+        assert_eq!(top(&out, 3), vec![0, 4, 7]);
+    }
+}
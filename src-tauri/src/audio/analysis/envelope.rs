@@ -0,0 +1,149 @@
+use crate::audio::types::AudioConfig;
+
+/// Per-band attack/release smoothing and falling peak markers.
+///
+/// Time constants come from the config in milliseconds and are converted
+/// with the frame interval, so the output moves at the same speed in real
+/// time whatever the analysis frame rate.
+pub struct EnvelopeFollower {
+    attack: f32,
+    release: f32,
+    frame_secs: f32,
+    hold_secs: f32,
+    /// Peak acceleration in full-scale units per second squared.
+    gravity: f32,
+    smoothed: Vec<f32>,
+    peaks: Vec<f32>,
+    /// Seconds since each peak was last pushed up.
+    peak_age: Vec<f32>,
+    peak_velocity: Vec<f32>,
+}
+
+/// Per-frame coefficient for a one-pole filter with time constant `ms`.
+fn coefficient(ms: f32, frame_secs: f32) -> f32 {
+    if ms <= 0.0 {
+        1.0
+    } else {
+        1.0 - (-frame_secs * 1000.0 / ms).exp()
+    }
+}
+
+impl EnvelopeFollower {
+    pub fn new(config: &AudioConfig, frame_rate: f32, len: usize) -> Self {
+        let frame_secs = 1.0 / frame_rate.max(1.0);
+        let fall_secs = config.peak_fall_ms / 1000.0;
+        Self {
+            attack: coefficient(config.attack_ms, frame_secs),
+            release: coefficient(config.release_ms, frame_secs),
+            frame_secs,
+            hold_secs: config.peak_hold_ms / 1000.0,
+            // Falls from full scale to zero in `fall_secs` once released
+            gravity: if fall_secs > 0.0 {
+                2.0 / (fall_secs * fall_secs)
+            } else {
+                f32::INFINITY
+            },
+            smoothed: vec![0.0; len],
+            peaks: vec![0.0; len],
+            peak_age: vec![0.0; len],
+            peak_velocity: vec![0.0; len],
+        }
+    }
+
+    pub fn process(&mut self, input: &[f32]) {
+        let dt = self.frame_secs;
+        for (i, &x) in input.iter().enumerate() {
+            let s = &mut self.smoothed[i];
+            let coeff = if x > *s { self.attack } else { self.release };
+            *s += (x - *s) * coeff;
+
+            let peak = &mut self.peaks[i];
+            if x >= *peak {
+                *peak = x;
+                self.peak_age[i] = 0.0;
+                self.peak_velocity[i] = 0.0;
+                continue;
+            }
+            self.peak_age[i] += dt;
+            if self.peak_age[i] > self.hold_secs {
+                self.peak_velocity[i] += self.gravity * dt;
+                *peak = (*peak - self.peak_velocity[i] * dt).max(x);
+            }
+        }
+    }
+
+    pub fn smoothed(&self) -> &[f32] {
+        &self.smoothed
+    }
+
+    pub fn peaks(&self) -> &[f32] {
+        &self.peaks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AudioConfig {
+        AudioConfig {
+            attack_ms: 20.0,
+            release_ms: 200.0,
+            peak_hold_ms: 250.0,
+            peak_fall_ms: 1000.0,
+            ..Default::default()
+        }
+    }
+
+    /// Runs one band through a 1.0 pulse lasting 0.5s, then silence, and
+    /// returns (smoothed, peak) at `at_secs`.
+    fn run(fps: f32, at_secs: f32) -> (f32, f32) {
+        let mut env = EnvelopeFollower::new(&config(), fps, 1);
+        let frames = (at_secs * fps).round() as usize;
+        for i in 0..frames {
+            let t = i as f32 / fps;
+            env.process(&[if t < 0.5 { 1.0 } else { 0.0 }]);
+        }
+        (env.smoothed()[0], env.peaks()[0])
+    }
+
+    #[test]
+    fn test_attack_is_fast_and_release_slow() {
+        let (rise, _) = run(60.0, 0.1);
+        assert!(rise > 0.95, "after 100ms of attack: {rise}");
+        let (fall, _) = run(60.0, 0.6);
+        assert!(fall > 0.5, "100ms into a 200ms release: {fall}");
+    }
+
+    #[test]
+    fn test_peak_holds_then_falls() {
+        // Held through 250ms after the pulse ends
+        let (_, held) = run(60.0, 0.7);
+        assert_eq!(held, 1.0);
+        // Falling, but not yet at the bottom
+        let (_, falling) = run(60.0, 1.25);
+        assert!(falling < 1.0 && falling > 0.0, "{falling}");
+        // Fully fallen a second after release
+        let (_, gone) = run(60.0, 1.9);
+        assert_eq!(gone, 0.0);
+    }
+
+    #[test]
+    fn test_independent_of_frame_rate() {
+        // Times on the 30 fps grid so every rate has run for the same time
+        for at in [1.0 / 30.0, 0.3, 0.6, 1.0, 1.3] {
+            let (s60, p60) = run(60.0, at);
+            for fps in [30.0, 120.0] {
+                let (s, p) = run(fps, at);
+                assert!(
+                    (s - s60).abs() < 0.05,
+                    "smoothed at {at}s, {fps} fps: {s} vs {s60}"
+                );
+                assert!(
+                    (p - p60).abs() < 0.05,
+                    "peak at {at}s, {fps} fps: {p} vs {p60}"
+                );
+            }
+        }
+    }
+}
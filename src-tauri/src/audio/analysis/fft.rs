@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use realfft::{num_complex::Complex, RealFftPlanner, RealToComplex};
+
+/// Hann-windowed real FFT giving magnitudes scaled so a full-scale sine
+/// peaks at 1.0. Plan and buffers are allocated up front.
+pub(super) struct MagnitudeFft {
+    fft: Arc<dyn RealToComplex<f32>>,
+    window: Vec<f32>,
+    /// Scales FFT output so a full-scale sine peaks at 1.0.
+    magnitude_norm: f32,
+    input: Vec<f32>,
+    output: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+}
+
+impl MagnitudeFft {
+    pub fn new(fft_size: usize) -> Self {
+        let window: Vec<f32> = apodize::hanning_iter(fft_size).map(|v| v as f32).collect();
+        let magnitude_norm = 2.0 / window.iter().sum::<f32>();
+        let fft = RealFftPlanner::<f32>::new().plan_fft_forward(fft_size);
+        Self {
+            input: fft.make_input_vec(),
+            output: fft.make_output_vec(),
+            scratch: fft.make_scratch_vec(),
+            fft,
+            window,
+            magnitude_norm,
+        }
+    }
+
+    /// Fills `magnitudes` (`fft_size / 2` bins, dropping Nyquist) from the
+    /// first `fft_size` samples.
+    pub fn process(&mut self, samples: &[f32], magnitudes: &mut [f32]) -> Option<()> {
+        for ((dst, &s), &w) in self.input.iter_mut().zip(samples).zip(&self.window) {
+            *dst = s * w;
+        }
+
+        // Only fails on mismatched buffer lengths, which `new` rules out
+        self.fft
+            .process_with_scratch(&mut self.input, &mut self.output, &mut self.scratch)
+            .ok()?;
+
+        for (m, c) in magnitudes.iter_mut().zip(&self.output) {
+            *m = c.norm() * self.magnitude_norm;
+        }
+        Some(())
+    }
+}
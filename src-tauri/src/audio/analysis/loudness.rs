@@ -0,0 +1,457 @@
+use serde::{Deserialize, Serialize};
+
+/// Blocks quieter than this are silence as far as R128 is concerned; it is
+/// also the floor reported before anything has been measured.
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+/// Integrated loudness ignores blocks this far below the ungated mean.
+const RELATIVE_GATE_LU: f64 = -10.0;
+/// Loudness range ignores short-term values this far below their mean.
+const RANGE_RELATIVE_GATE_LU: f64 = -20.0;
+/// Momentary and short-term loudness advance in steps of this length.
+const SUB_BLOCK_SECS: f64 = 0.1;
+/// Sub-blocks in the 400 ms momentary window.
+const MOMENTARY_SUB_BLOCKS: usize = 4;
+/// Sub-blocks in the 3 s short-term window.
+const SHORT_TERM_SUB_BLOCKS: usize = 30;
+/// Gated measurements are kept as 0.1 LU histograms from the absolute gate
+/// up to +10 LUFS, so they cost the same after hours as after seconds.
+const HISTOGRAM_STEP_LU: f64 = 0.1;
+const HISTOGRAM_BINS: usize = 800;
+const OVERSAMPLING: usize = 4;
+const TAPS_PER_PHASE: usize = 12;
+const TRUE_PEAK_FLOOR_DB: f32 = -100.0;
+
+/// EBU R128 loudness readings.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Loudness {
+    /// Over the last 400 ms, in LUFS.
+    pub momentary: f32,
+    /// Over the last 3 s, in LUFS.
+    pub short_term: f32,
+    /// Gated average since metering started, in LUFS.
+    pub integrated: f32,
+    /// Spread of short-term loudness (10th to 95th percentile), in LU.
+    pub range: f32,
+    /// Highest 4x-oversampled peak since metering started, in dBTP.
+    pub true_peak: f32,
+}
+
+impl Default for Loudness {
+    fn default() -> Self {
+        Self {
+            momentary: ABSOLUTE_GATE_LUFS as f32,
+            short_term: ABSOLUTE_GATE_LUFS as f32,
+            integrated: ABSOLUTE_GATE_LUFS as f32,
+            range: 0.0,
+            true_peak: TRUE_PEAK_FLOOR_DB,
+        }
+    }
+}
+
+fn power_to_lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.max(1e-20).log10()
+}
+
+/// Transposed direct form II biquad.
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// The two K-weighting stages from BS.1770, derived for any sample rate
+/// (the standard only tabulates 48 kHz).
+fn k_weighting(sample_rate: f64) -> (Biquad, Biquad) {
+    // High shelf modelling the head
+    let f0 = 1681.974450955533;
+    let gain_db = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (std::f64::consts::PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    // RLB high-pass
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (std::f64::consts::PI * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let highpass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    (shelf, highpass)
+}
+
+/// Counts and summed powers of gated blocks, binned by loudness.
+struct Histogram {
+    counts: Vec<u64>,
+    powers: Vec<f64>,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            counts: vec![0; HISTOGRAM_BINS],
+            powers: vec![0.0; HISTOGRAM_BINS],
+        }
+    }
+
+    fn bin(lufs: f64) -> usize {
+        (((lufs - ABSOLUTE_GATE_LUFS) / HISTOGRAM_STEP_LU).max(0.0) as usize)
+            .min(HISTOGRAM_BINS - 1)
+    }
+
+    fn bin_lufs(bin: usize) -> f64 {
+        ABSOLUTE_GATE_LUFS + (bin as f64 + 0.5) * HISTOGRAM_STEP_LU
+    }
+
+    /// Adds a block if it passes the absolute gate.
+    fn add(&mut self, power: f64) {
+        let lufs = power_to_lufs(power);
+        if lufs >= ABSOLUTE_GATE_LUFS {
+            let bin = Self::bin(lufs);
+            self.counts[bin] += 1;
+            self.powers[bin] += power;
+        }
+    }
+
+    /// Mean power of the blocks at or above `from`, with their count.
+    fn mean_power(&self, from: usize) -> Option<(f64, u64)> {
+        let count: u64 = self.counts[from..].iter().sum();
+        (count > 0).then(|| {
+            (
+                self.powers[from..].iter().sum::<f64>() / count as f64,
+                count,
+            )
+        })
+    }
+
+    /// First bin whose cumulative count from `from` reaches `fraction` of `total`.
+    fn percentile(&self, from: usize, total: u64, fraction: f64) -> f64 {
+        let target = (fraction * (total - 1) as f64).round() as u64;
+        let mut seen = 0;
+        for bin in from..HISTOGRAM_BINS {
+            seen += self.counts[bin];
+            if seen > target {
+                return Self::bin_lufs(bin);
+            }
+        }
+        Self::bin_lufs(HISTOGRAM_BINS - 1)
+    }
+}
+
+/// Filter and interpolation state for one input channel.
+struct Channel {
+    shelf: Biquad,
+    highpass: Biquad,
+    history: [f32; TAPS_PER_PHASE],
+    history_pos: usize,
+}
+
+impl Channel {
+    fn new(sample_rate: u32) -> Self {
+        let (shelf, highpass) = k_weighting(sample_rate as f64);
+        Self {
+            shelf,
+            highpass,
+            history: [0.0; TAPS_PER_PHASE],
+            history_pos: 0,
+        }
+    }
+
+    /// K-weighted power of one sample.
+    fn weighted_power(&mut self, s: f32) -> f64 {
+        let weighted = self.highpass.process(self.shelf.process(s as f64));
+        weighted * weighted
+    }
+
+    /// Largest interpolated magnitude between the previous sample and `s`.
+    fn true_peak(&mut self, s: f32, phases: &[[f32; TAPS_PER_PHASE]; OVERSAMPLING]) -> f32 {
+        self.history[self.history_pos] = s;
+        self.history_pos = (self.history_pos + 1) % TAPS_PER_PHASE;
+        let mut peak = 0.0f32;
+        for taps in phases {
+            // history_pos now points at the oldest sample; tap 0 takes the newest
+            let y: f32 = taps
+                .iter()
+                .enumerate()
+                .map(|(k, t)| {
+                    t * self.history[(self.history_pos + TAPS_PER_PHASE - 1 - k) % TAPS_PER_PHASE]
+                })
+                .sum();
+            peak = peak.max(y.abs());
+        }
+        peak
+    }
+}
+
+/// Streaming EBU R128 loudness and true-peak meter.
+///
+/// Takes every sample exactly once, unlike the FFT analysis which sees
+/// overlapping blocks. Input is either one channel or a left/right pair.
+pub struct LoudnessMeter {
+    channels: [Channel; 2],
+    sub_block_len: usize,
+    sub_block_pos: usize,
+    sub_block_sum: f64,
+    /// Mean squares of the latest sub-blocks, oldest overwritten first.
+    sub_blocks: [f64; SHORT_TERM_SUB_BLOCKS],
+    sub_block_count: usize,
+    blocks: Histogram,
+    short_terms: Histogram,
+    readings: Loudness,
+    /// Windowed-sinc interpolation filter, one row of taps per phase.
+    phases: [[f32; TAPS_PER_PHASE]; OVERSAMPLING],
+    peak: f32,
+    sample_rate: u32,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: u32) -> Self {
+        // Output phase p sits p/4 of a sample after the filter's centre tap
+        let centre = (TAPS_PER_PHASE / 2 - 1) as f32;
+        let mut phases = [[0.0; TAPS_PER_PHASE]; OVERSAMPLING];
+        for (p, taps) in phases.iter_mut().enumerate() {
+            for (k, tap) in taps.iter_mut().enumerate() {
+                let t = k as f32 - centre - p as f32 / OVERSAMPLING as f32;
+                let sinc = if t == 0.0 {
+                    1.0
+                } else {
+                    (std::f32::consts::PI * t).sin() / (std::f32::consts::PI * t)
+                };
+                let hann =
+                    0.5 + 0.5 * (std::f32::consts::PI * t / (TAPS_PER_PHASE / 2) as f32).cos();
+                *tap = sinc * hann;
+            }
+            let sum: f32 = taps.iter().sum();
+            taps.iter_mut().for_each(|t| *t /= sum);
+        }
+
+        Self {
+            channels: [Channel::new(sample_rate), Channel::new(sample_rate)],
+            sub_block_len: ((sample_rate as f64 * SUB_BLOCK_SECS).round() as usize).max(1),
+            sub_block_pos: 0,
+            sub_block_sum: 0.0,
+            sub_blocks: [0.0; SHORT_TERM_SUB_BLOCKS],
+            sub_block_count: 0,
+            blocks: Histogram::new(),
+            short_terms: Histogram::new(),
+            readings: Loudness::default(),
+            phases,
+            peak: 0.0,
+            sample_rate,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Starts over, as for a new track.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.sample_rate);
+    }
+
+    /// Meters a single channel.
+    pub fn process(&mut self, samples: &[f32]) {
+        for &s in samples {
+            let peak = self.channels[0].true_peak(s, &self.phases);
+            self.peak = self.peak.max(peak);
+            let power = self.channels[0].weighted_power(s);
+            self.add_power(power);
+        }
+    }
+
+    /// Meters a left/right pair; channel powers add, as BS.1770 sums them.
+    pub fn process_stereo(&mut self, left: &[f32], right: &[f32]) {
+        for (&l, &r) in left.iter().zip(right) {
+            let [left_channel, right_channel] = &mut self.channels;
+            let peak = left_channel
+                .true_peak(l, &self.phases)
+                .max(right_channel.true_peak(r, &self.phases));
+            self.peak = self.peak.max(peak);
+            let power = left_channel.weighted_power(l) + right_channel.weighted_power(r);
+            self.add_power(power);
+        }
+    }
+
+    fn add_power(&mut self, power: f64) {
+        self.sub_block_sum += power;
+        self.sub_block_pos += 1;
+        if self.sub_block_pos == self.sub_block_len {
+            self.finish_sub_block();
+        }
+    }
+
+    fn finish_sub_block(&mut self) {
+        let mean_square = self.sub_block_sum / self.sub_block_len as f64;
+        self.sub_blocks[self.sub_block_count % SHORT_TERM_SUB_BLOCKS] = mean_square;
+        self.sub_block_count += 1;
+        self.sub_block_pos = 0;
+        self.sub_block_sum = 0.0;
+
+        let window_power = |n: usize| {
+            (0..n)
+                .map(|i| self.sub_blocks[(self.sub_block_count - 1 - i) % SHORT_TERM_SUB_BLOCKS])
+                .sum::<f64>()
+                / n as f64
+        };
+
+        if self.sub_block_count >= MOMENTARY_SUB_BLOCKS {
+            let power = window_power(MOMENTARY_SUB_BLOCKS);
+            self.readings.momentary = power_to_lufs(power).max(ABSOLUTE_GATE_LUFS) as f32;
+            self.blocks.add(power);
+        }
+        if self.sub_block_count >= SHORT_TERM_SUB_BLOCKS {
+            let power = window_power(SHORT_TERM_SUB_BLOCKS);
+            self.readings.short_term = power_to_lufs(power).max(ABSOLUTE_GATE_LUFS) as f32;
+            self.short_terms.add(power);
+        }
+
+        self.update_integrated();
+        self.update_range();
+    }
+
+    fn update_integrated(&mut self) {
+        let Some((ungated, _)) = self.blocks.mean_power(0) else {
+            return;
+        };
+        let gate = Histogram::bin(power_to_lufs(ungated) + RELATIVE_GATE_LU);
+        if let Some((gated, _)) = self.blocks.mean_power(gate) {
+            self.readings.integrated = power_to_lufs(gated) as f32;
+        }
+    }
+
+    fn update_range(&mut self) {
+        let Some((ungated, _)) = self.short_terms.mean_power(0) else {
+            return;
+        };
+        let gate_lufs = power_to_lufs(ungated) + RANGE_RELATIVE_GATE_LU;
+        let gate = Histogram::bin(gate_lufs.max(ABSOLUTE_GATE_LUFS));
+        if let Some((_, count)) = self.short_terms.mean_power(gate) {
+            let low = self.short_terms.percentile(gate, count, 0.10);
+            let high = self.short_terms.percentile(gate, count, 0.95);
+            self.readings.range = (high - low) as f32;
+        }
+    }
+
+    pub fn loudness(&self) -> Loudness {
+        Loudness {
+            true_peak: (20.0 * self.peak.max(1e-10).log10()).max(TRUE_PEAK_FLOOR_DB),
+            ..self.readings
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    fn sine(freq: f32, amplitude: f32, sample_rate: u32, secs: f32) -> Vec<f32> {
+        let len = (secs * sample_rate as f32) as usize;
+        (0..len)
+            .map(|i| amplitude * (TAU * freq * i as f32 / sample_rate as f32).sin())
+            .collect()
+    }
+
+    /// Amplitude of a 1 kHz sine that reads `lufs`. K-weighting at 1 kHz
+    /// cancels the -0.691 offset, leaving the sine's mean square of A²/2.
+    fn sine_amplitude_for(lufs: f32) -> f32 {
+        (2.0 * 10f32.powf(lufs / 10.0)).sqrt()
+    }
+
+    #[test]
+    fn test_full_scale_sine_reads_minus_3_lufs() {
+        // BS.1770: a 0 dBFS 1 kHz sine in one channel reads -3.01 LUFS
+        for sample_rate in [44100, 48000, 96000] {
+            let mut meter = LoudnessMeter::new(sample_rate);
+            meter.process(&sine(997.0, 1.0, sample_rate, 5.0));
+            let l = meter.loudness();
+            for (name, value) in [
+                ("momentary", l.momentary),
+                ("short-term", l.short_term),
+                ("integrated", l.integrated),
+            ] {
+                assert!(
+                    (value + 3.01).abs() < 0.1,
+                    "{name} at {sample_rate}: {value}"
+                );
+            }
+            assert!(l.range < 0.5, "range {}", l.range);
+            assert!(l.true_peak.abs() < 0.1, "true peak {}", l.true_peak);
+        }
+    }
+
+    #[test]
+    fn test_stereo_sums_channel_powers() {
+        // The same sine in both channels is 3 dB louder than in one
+        let samples = sine(997.0, 1.0, 48000, 5.0);
+        let mut meter = LoudnessMeter::new(48000);
+        meter.process_stereo(&samples, &samples);
+        let l = meter.loudness();
+        assert!(l.integrated.abs() < 0.1, "{}", l.integrated);
+        assert!(l.true_peak.abs() < 0.1, "{}", l.true_peak);
+
+        let mut meter = LoudnessMeter::new(48000);
+        meter.process_stereo(&samples, &vec![0.0; samples.len()]);
+        assert!((meter.loudness().integrated + 3.01).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_silence_is_gated_out_of_integrated() {
+        let mut meter = LoudnessMeter::new(48000);
+        meter.process(&sine(1000.0, sine_amplitude_for(-23.0), 48000, 10.0));
+        meter.process(&vec![0.0; 48000 * 10]);
+        let l = meter.loudness();
+        assert!((l.integrated + 23.0).abs() < 0.2, "{}", l.integrated);
+        assert_eq!(l.momentary, ABSOLUTE_GATE_LUFS as f32);
+    }
+
+    #[test]
+    fn test_range_of_two_levels() {
+        // EBU Tech 3342 case 1: 20 s at -20 then 20 s at -30 gives 10 LU
+        let mut meter = LoudnessMeter::new(48000);
+        meter.process(&sine(1000.0, sine_amplitude_for(-20.0), 48000, 20.0));
+        meter.process(&sine(1000.0, sine_amplitude_for(-30.0), 48000, 20.0));
+        let range = meter.loudness().range;
+        assert!((range - 10.0).abs() < 1.0, "{range}");
+    }
+
+    #[test]
+    fn test_true_peak_finds_inter_sample_peak() {
+        // fs/4 sine at 45 degrees: every sample lands at +-0.707
+        let samples: Vec<f32> = (0..4800)
+            .map(|i| (TAU * 0.25 * i as f32 + TAU / 8.0).sin())
+            .collect();
+        let sample_peak = samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!(20.0 * sample_peak.log10() < -2.9);
+
+        let mut meter = LoudnessMeter::new(48000);
+        meter.process(&samples);
+        let true_peak = meter.loudness().true_peak;
+        assert!(true_peak > -0.5 && true_peak < 0.5, "{true_peak}");
+    }
+}
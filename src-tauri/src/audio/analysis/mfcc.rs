@@ -0,0 +1,128 @@
+use super::bands::{mel_edges, MAX_BAND_HZ, MIN_BAND_HZ};
+
+/// Cepstral coefficients kept, including c0 (overall log energy).
+pub const MFCC_COEFFICIENTS: usize = 13;
+/// Triangular mel filters the spectrum is reduced to before the DCT.
+const MEL_FILTERS: usize = 26;
+
+/// Mel-frequency cepstral coefficients: mel filterbank energies, log, then
+/// an orthonormal DCT-II. Filters and DCT basis are built once.
+pub struct Mfcc {
+    /// `(bin, weight)` pairs for every filter, back to back.
+    weights: Vec<(usize, f32)>,
+    /// Filter `i` uses `weights[offsets[i]..offsets[i + 1]]`.
+    offsets: Vec<usize>,
+    /// Row-major `MFCC_COEFFICIENTS x MEL_FILTERS`.
+    dct: Vec<f32>,
+    log_energies: Vec<f32>,
+}
+
+impl Mfcc {
+    pub fn new(spectrum_len: usize, bin_hz: f32) -> Self {
+        let max_hz = MAX_BAND_HZ.min(bin_hz * spectrum_len as f32);
+        let edges = mel_edges(MEL_FILTERS + 1, MIN_BAND_HZ, max_hz);
+
+        let mut weights = Vec::new();
+        let mut offsets = vec![0];
+        for e in edges.windows(3) {
+            let (lo, center, hi) = (e[0], e[1], e[2]);
+            let start = weights.len();
+            let first = (lo / bin_hz).ceil() as usize;
+            let last = ((hi / bin_hz).floor() as usize).min(spectrum_len - 1);
+            for bin in first..=last {
+                let hz = bin as f32 * bin_hz;
+                let w = if hz <= center {
+                    (hz - lo) / (center - lo)
+                } else {
+                    (hi - hz) / (hi - center)
+                };
+                if w > 0.0 {
+                    weights.push((bin, w));
+                }
+            }
+            // Low filters can fall between bins at small FFT sizes
+            if weights.len() == start {
+                let nearest = ((center / bin_hz).round() as usize).min(spectrum_len - 1);
+                weights.push((nearest, 1.0));
+            }
+            offsets.push(weights.len());
+        }
+
+        let n = MEL_FILTERS as f32;
+        let dct = (0..MFCC_COEFFICIENTS)
+            .flat_map(|k| {
+                let scale = if k == 0 {
+                    (1.0 / n).sqrt()
+                } else {
+                    (2.0 / n).sqrt()
+                };
+                (0..MEL_FILTERS).map(move |i| {
+                    scale * (std::f32::consts::PI * k as f32 * (i as f32 + 0.5) / n).cos()
+                })
+            })
+            .collect();
+
+        Self {
+            weights,
+            offsets,
+            dct,
+            log_energies: vec![0.0; MEL_FILTERS],
+        }
+    }
+
+    /// Fills `out` (`MFCC_COEFFICIENTS` long) from absolute magnitudes.
+    pub fn compute(&mut self, magnitudes: &[f32], out: &mut [f32]) {
+        for (i, e) in self.log_energies.iter_mut().enumerate() {
+            let energy: f32 = self.weights[self.offsets[i]..self.offsets[i + 1]]
+                .iter()
+                .map(|&(bin, w)| magnitudes[bin] * magnitudes[bin] * w)
+                .sum();
+            *e = (energy + 1e-10).ln();
+        }
+        for (k, c) in out.iter_mut().enumerate() {
+            let row = &self.dct[k * MEL_FILTERS..(k + 1) * MEL_FILTERS];
+            *c = row.iter().zip(&self.log_energies).map(|(d, e)| d * e).sum();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BIN_HZ: f32 = 48000.0 / 2048.0;
+
+    fn mfcc_of(magnitudes: &[f32]) -> Vec<f32> {
+        let mut mfcc = Mfcc::new(magnitudes.len(), BIN_HZ);
+        let mut out = vec![0.0; MFCC_COEFFICIENTS];
+        mfcc.compute(magnitudes, &mut out);
+        out
+    }
+
+    #[test]
+    fn test_gain_only_moves_c0() {
+        // Tilted spectrum so the shape coefficients aren't trivially zero
+        let quiet: Vec<f32> = (0..1024).map(|i| 0.1 / (1.0 + i as f32 * 0.01)).collect();
+        let loud: Vec<f32> = quiet.iter().map(|m| m * 10.0).collect();
+        let (a, b) = (mfcc_of(&quiet), mfcc_of(&loud));
+
+        assert!(b[0] > a[0] + 1.0);
+        for k in 1..MFCC_COEFFICIENTS {
+            assert!((a[k] - b[k]).abs() < 1e-3, "c{k}: {} vs {}", a[k], b[k]);
+        }
+    }
+
+    #[test]
+    fn test_dark_and_bright_spectra_differ_in_c1() {
+        let dark: Vec<f32> = (0..1024).map(|i| 1.0 / (1.0 + i as f32 * 0.05)).collect();
+        let bright: Vec<f32> = (0..1024).map(|i| i as f32 / 1024.0).collect();
+        // c1 weighs low filters against high ones
+        assert!(mfcc_of(&dark)[1] > mfcc_of(&bright)[1]);
+    }
+
+    #[test]
+    fn test_every_filter_has_weights_at_small_fft() {
+        let mfcc = Mfcc::new(128, 48000.0 / 256.0);
+        assert!(mfcc.offsets.windows(2).all(|o| o[1] > o[0]));
+    }
+}
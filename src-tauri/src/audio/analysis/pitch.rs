@@ -0,0 +1,199 @@
+use serde::{Deserialize, Serialize};
+
+use crate::audio::key::PITCH_CLASSES;
+
+/// Range of fundamentals searched: low male voice / bass guitar up to the
+/// top of most lead instruments.
+const MIN_PITCH_HZ: f32 = 50.0;
+const MAX_PITCH_HZ: f32 = 2000.0;
+/// Cumulative-mean-normalized difference a lag has to dip below to count
+/// as periodic. YIN's paper uses 0.10–0.15.
+const YIN_THRESHOLD: f32 = 0.15;
+/// Samples the difference function sums over, at most.
+const MAX_INTEGRATION: usize = 2048;
+/// Mean square below which a block is treated as silence.
+const SILENCE_POWER: f32 = 1e-8;
+
+/// YIN fundamental frequency estimator for monophonic input.
+///
+/// The lag range is fixed at construction, so `detect` only fills a
+/// preallocated buffer. Blocks shorter than twice the longest period raise
+/// the lowest detectable pitch rather than failing.
+pub struct PitchTracker {
+    sample_rate: f32,
+    tau_min: usize,
+    tau_max: usize,
+    /// Cumulative-mean-normalized difference, indexed by lag.
+    cmnd: Vec<f32>,
+}
+
+impl PitchTracker {
+    pub fn new(block_len: usize, sample_rate: u32) -> Self {
+        let sr = sample_rate as f32;
+        let tau_max = ((sr / MIN_PITCH_HZ).ceil() as usize).min(block_len / 2);
+        let tau_min = ((sr / MAX_PITCH_HZ).floor() as usize).clamp(2, tau_max.max(2));
+        Self {
+            sample_rate: sr,
+            tau_min,
+            tau_max,
+            cmnd: vec![1.0; tau_max + 2],
+        }
+    }
+
+    /// Returns `(f0 in Hz, confidence 0..1)` for the last samples of
+    /// `samples`, or `None` when the block is silent or not periodic.
+    pub fn detect(&mut self, samples: &[f32]) -> Option<(f32, f32)> {
+        let (tau_min, tau_max) = (self.tau_min, self.tau_max);
+        if tau_max <= tau_min + 1 || samples.len() < 2 * tau_max {
+            return None;
+        }
+        let window = (samples.len() - tau_max).min(MAX_INTEGRATION);
+        let x = &samples[samples.len() - window - tau_max..];
+
+        let power = x[..window].iter().map(|s| s * s).sum::<f32>() / window as f32;
+        if power < SILENCE_POWER {
+            return None;
+        }
+
+        // Difference function, normalized by its running mean as it goes
+        self.cmnd[0] = 1.0;
+        let mut running = 0.0;
+        for tau in 1..=tau_max {
+            let d: f32 = x[..window]
+                .iter()
+                .zip(&x[tau..tau + window])
+                .map(|(a, b)| (a - b) * (a - b))
+                .sum();
+            running += d;
+            self.cmnd[tau] = if running > 0.0 {
+                d * tau as f32 / running
+            } else {
+                1.0
+            };
+        }
+
+        // First dip under the threshold, followed down to its minimum
+        let mut tau = (tau_min..tau_max).find(|&t| self.cmnd[t] < YIN_THRESHOLD)?;
+        while tau + 1 < tau_max && self.cmnd[tau + 1] < self.cmnd[tau] {
+            tau += 1;
+        }
+
+        // Parabolic interpolation for a sub-sample period
+        let (a, b, c) = (self.cmnd[tau - 1], self.cmnd[tau], self.cmnd[tau + 1]);
+        let denom = a - 2.0 * b + c;
+        let offset = if denom.abs() > 1e-12 {
+            (0.5 * (a - c) / denom).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        };
+        let period = tau as f32 + offset;
+        Some((self.sample_rate / period, (1.0 - b).clamp(0.0, 1.0)))
+    }
+}
+
+/// A detected fundamental with its nearest equal-tempered note.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pitch {
+    /// Fundamental in Hz.
+    pub frequency: f32,
+    /// MIDI note number of the nearest note, 69 = A4.
+    pub midi: i32,
+    /// Nearest note in scientific pitch notation, e.g. "A4".
+    pub note: String,
+    /// Distance from the nearest note, -50..50.
+    pub cents: f32,
+    pub confidence: f32,
+}
+
+impl Pitch {
+    pub fn from_frequency(frequency: f32, confidence: f32) -> Self {
+        let exact = 69.0 + 12.0 * (frequency / 440.0).log2();
+        let midi = exact.round() as i32;
+        let note = format!(
+            "{}{}",
+            PITCH_CLASSES[midi.rem_euclid(12) as usize],
+            midi.div_euclid(12) - 1
+        );
+        Self {
+            frequency,
+            midi,
+            note,
+            cents: (exact - midi as f32) * 100.0,
+            confidence,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    fn tone(harmonics: &[(f32, f32)], sample_rate: u32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                harmonics
+                    .iter()
+                    .map(|&(f, a)| a * (TAU * f * t).sin())
+                    .sum()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sines_across_the_range() {
+        for sample_rate in [44100, 48000] {
+            let mut tracker = PitchTracker::new(4096, sample_rate);
+            for freq in [55.0, 110.0, 261.63, 440.0, 1000.0, 1760.0] {
+                let samples = tone(&[(freq, 0.5)], sample_rate, 4096);
+                let (f0, confidence) = tracker.detect(&samples).unwrap();
+                assert!(
+                    (f0 - freq).abs() / freq < 0.005,
+                    "{freq} Hz at {sample_rate}: {f0}"
+                );
+                assert!(confidence > 0.9, "{freq} Hz: confidence {confidence}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_harmonic_tone_is_not_an_octave_off() {
+        // Weak fundamental under strong upper harmonics
+        let samples = tone(
+            &[(220.0, 0.2), (440.0, 0.5), (660.0, 0.4), (880.0, 0.3)],
+            48000,
+            2048,
+        );
+        let (f0, _) = PitchTracker::new(2048, 48000).detect(&samples).unwrap();
+        assert!((f0 - 220.0).abs() < 2.0, "{f0}");
+    }
+
+    #[test]
+    fn test_noise_and_silence_are_unvoiced() {
+        let mut tracker = PitchTracker::new(2048, 48000);
+        assert!(tracker.detect(&[0.0; 2048]).is_none());
+
+        let mut rng = fastrand::Rng::with_seed(3);
+        let noise: Vec<f32> = (0..2048).map(|_| rng.f32() * 2.0 - 1.0).collect();
+        assert!(tracker.detect(&noise).is_none());
+    }
+
+    #[test]
+    fn test_note_names_and_cents() {
+        let a4 = Pitch::from_frequency(440.0, 1.0);
+        assert_eq!((a4.midi, a4.note.as_str()), (69, "A4"));
+        assert!(a4.cents.abs() < 1e-3);
+
+        let sharp = Pitch::from_frequency(445.0, 1.0);
+        assert_eq!(sharp.note, "A4");
+        assert!((sharp.cents - 19.56).abs() < 0.1, "{}", sharp.cents);
+
+        let flat_c = Pitch::from_frequency(258.0, 1.0);
+        assert_eq!(flat_c.note, "C4");
+        assert!(flat_c.cents < 0.0);
+
+        assert_eq!(Pitch::from_frequency(55.0, 1.0).note, "A1");
+    }
+}
@@ -0,0 +1,134 @@
+use crate::audio::types::{AudioConfig, SpectrumScale};
+
+/// Time for automatic gain to back off when the signal gets louder.
+const AGC_ATTACK_SECS: f32 = 1.0;
+/// Time for automatic gain to recover when the signal gets quieter.
+const AGC_RELEASE_SECS: f32 = 8.0;
+/// Room left between the loudest bin and the ceiling under automatic gain.
+const AGC_HEADROOM_DB: f32 = 6.0;
+const AGC_MIN_GAIN_DB: f32 = -24.0;
+const AGC_MAX_GAIN_DB: f32 = 48.0;
+
+/// Maps linear magnitudes to 0..1 display values on a fixed dB scale,
+/// optionally riding a slow gain so quiet material still fills the range.
+pub struct SpectrumScaler {
+    scale: SpectrumScale,
+    floor_db: f32,
+    ceiling_db: f32,
+    auto_gain: bool,
+    gain_db: f32,
+    attack: f32,
+    release: f32,
+}
+
+impl SpectrumScaler {
+    pub fn new(config: &AudioConfig, frame_rate: f32) -> Self {
+        let frame_secs = 1.0 / frame_rate.max(1.0);
+        Self {
+            scale: config.spectrum_scale,
+            floor_db: config.db_floor,
+            ceiling_db: config.db_ceiling,
+            auto_gain: config.auto_gain,
+            gain_db: 0.0,
+            attack: 1.0 - (-frame_secs / AGC_ATTACK_SECS).exp(),
+            release: 1.0 - (-frame_secs / AGC_RELEASE_SECS).exp(),
+        }
+    }
+
+    pub fn scale(&self) -> SpectrumScale {
+        self.scale
+    }
+
+    /// Current automatic gain in dB; 0 when it's off.
+    pub fn gain_db(&self) -> f32 {
+        self.gain_db
+    }
+
+    /// Rescales `values` (linear magnitudes, 1.0 = full-scale sine) in place.
+    pub fn apply(&mut self, values: &mut [f32]) {
+        if self.scale != SpectrumScale::Decibel {
+            return;
+        }
+
+        for v in values.iter_mut() {
+            *v = 20.0 * v.max(1e-10).log10();
+        }
+
+        if self.auto_gain {
+            self.update_gain(values);
+        }
+
+        let range = self.ceiling_db - self.floor_db;
+        for v in values.iter_mut() {
+            *v = ((*v + self.gain_db - self.floor_db) / range).clamp(0.0, 1.0);
+        }
+    }
+
+    fn update_gain(&mut self, levels_db: &[f32]) {
+        let peak = levels_db.iter().cloned().fold(f32::MIN, f32::max);
+        // Hold the gain through silence rather than cranking it up on the noise floor
+        if peak < self.floor_db {
+            return;
+        }
+        let target =
+            (self.ceiling_db - AGC_HEADROOM_DB - peak).clamp(AGC_MIN_GAIN_DB, AGC_MAX_GAIN_DB);
+        let coeff = if target < self.gain_db {
+            self.attack
+        } else {
+            self.release
+        };
+        self.gain_db += (target - self.gain_db) * coeff;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decibel_config(auto_gain: bool) -> AudioConfig {
+        AudioConfig {
+            spectrum_scale: SpectrumScale::Decibel,
+            db_floor: -80.0,
+            db_ceiling: 0.0,
+            auto_gain,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_fixed_scale_keeps_absolute_level() {
+        let mut scaler = SpectrumScaler::new(&decibel_config(false), 60.0);
+        // 0 dB, -40 dB, and below the floor
+        let mut values = [1.0, 0.01, 1e-6];
+        scaler.apply(&mut values);
+        assert!((values[0] - 1.0).abs() < 1e-4);
+        assert!((values[1] - 0.5).abs() < 1e-4);
+        assert_eq!(values[2], 0.0);
+    }
+
+    #[test]
+    fn test_auto_gain_lifts_quiet_signal_slowly() {
+        let mut scaler = SpectrumScaler::new(&decibel_config(true), 60.0);
+        let quiet = 0.00316; // -50 dB
+
+        let mut values = [quiet];
+        scaler.apply(&mut values);
+        let first = values[0];
+
+        // A quarter of a second in, it has barely moved
+        for _ in 0..15 {
+            values = [quiet];
+            scaler.apply(&mut values);
+        }
+        assert!(scaler.gain_db() < 10.0);
+
+        // After a minute it has settled with the peak just under the ceiling
+        for _ in 0..60 * 60 {
+            values = [quiet];
+            scaler.apply(&mut values);
+        }
+        assert!((scaler.gain_db() - (50.0 - AGC_HEADROOM_DB)).abs() < 1.0);
+        assert!(values[0] > first);
+        assert!(values[0] < 1.0);
+    }
+}
@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+
+use super::bands::BandMapper;
+use super::fft::MagnitudeFft;
+use super::scale::SpectrumScaler;
+use crate::audio::types::{AudioConfig, SpectrumScale};
+
+/// Consecutive samples drawn on the goniometer each frame.
+const GONIOMETER_POINTS: usize = 512;
+
+/// Left/right picture of one block.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StereoResult {
+    /// Left channel spectrum, laid out and scaled like `AudioFrame::spectrum`.
+    pub left: Vec<f32>,
+    pub right: Vec<f32>,
+    /// Phase correlation, -1 (out of phase) through 0 (unrelated) to 1 (mono).
+    pub correlation: f32,
+    /// Energy balance, -1 (all left) to 1 (all right).
+    pub balance: f32,
+    /// RMS of the mid signal, (L + R) / 2.
+    pub mid: f32,
+    /// RMS of the side signal, (L - R) / 2.
+    pub side: f32,
+    /// Latest samples as `[side, mid]` points rotated 45 degrees, so mono
+    /// draws a vertical line and a hard-left signal leans left.
+    pub goniometer: Vec<[f32; 2]>,
+}
+
+/// Per-channel spectra and stereo image measures. Like [`super::AudioAnalyzer`]
+/// it allocates up front and reuses its result.
+pub struct StereoAnalyzer {
+    fft_size: usize,
+    fft: MagnitudeFft,
+    bin_hz: f32,
+    left_magnitudes: Vec<f32>,
+    right_magnitudes: Vec<f32>,
+    band_mapper: Option<BandMapper>,
+    scaler: SpectrumScaler,
+    /// Both display spectra back to back, so one scaler pass (and one
+    /// automatic gain) covers both channels.
+    display: Vec<f32>,
+    result: StereoResult,
+}
+
+impl StereoAnalyzer {
+    pub fn new(fft_size: usize, sample_rate: u32) -> Self {
+        let spectrum_len = fft_size / 2;
+        Self {
+            fft_size,
+            fft: MagnitudeFft::new(fft_size),
+            bin_hz: sample_rate as f32 / fft_size as f32,
+            left_magnitudes: vec![0.0; spectrum_len],
+            right_magnitudes: vec![0.0; spectrum_len],
+            band_mapper: None,
+            scaler: SpectrumScaler::new(&AudioConfig::default(), 60.0),
+            display: vec![0.0; spectrum_len * 2],
+            result: StereoResult {
+                left: vec![0.0; spectrum_len],
+                right: vec![0.0; spectrum_len],
+                goniometer: Vec::with_capacity(GONIOMETER_POINTS),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Matches the spectrum layout and scale of an `AudioAnalyzer` given
+    /// the same settings.
+    pub fn configure(&mut self, config: &AudioConfig, frame_rate: f32) {
+        let spectrum_len = self.fft_size / 2;
+        self.band_mapper = BandMapper::new(
+            config.spectrum_layout,
+            config.band_count,
+            spectrum_len,
+            self.bin_hz,
+        );
+        let len = self
+            .band_mapper
+            .as_ref()
+            .map_or(spectrum_len, |m| m.bands().len());
+        self.scaler = SpectrumScaler::new(config, frame_rate);
+        self.display = vec![0.0; len * 2];
+        self.result.left = vec![0.0; len];
+        self.result.right = vec![0.0; len];
+    }
+
+    /// Analyzes the first `fft_size` samples of each channel.
+    pub fn analyze(&mut self, left: &[f32], right: &[f32]) -> Option<&StereoResult> {
+        let n = self.fft_size;
+        if left.len() < n || right.len() < n {
+            return None;
+        }
+        let (left, right) = (&left[..n], &right[..n]);
+
+        self.fft.process(left, &mut self.left_magnitudes)?;
+        self.fft.process(right, &mut self.right_magnitudes)?;
+
+        // Normalized spectra share one peak so the channels stay comparable
+        if self.scaler.scale() == SpectrumScale::Normalized {
+            let max = self
+                .left_magnitudes
+                .iter()
+                .chain(&self.right_magnitudes)
+                .cloned()
+                .fold(0.0f32, f32::max);
+            let inv_max = if max > 0.0 { 1.0 / max } else { 0.0 };
+            for m in self
+                .left_magnitudes
+                .iter_mut()
+                .chain(&mut self.right_magnitudes)
+            {
+                *m *= inv_max;
+            }
+        }
+
+        let len = self.result.left.len();
+        let (left_display, right_display) = self.display.split_at_mut(len);
+        match &self.band_mapper {
+            Some(mapper) => {
+                mapper.map(&self.left_magnitudes, left_display);
+                mapper.map(&self.right_magnitudes, right_display);
+            }
+            None => {
+                left_display.copy_from_slice(&self.left_magnitudes);
+                right_display.copy_from_slice(&self.right_magnitudes);
+            }
+        }
+        self.scaler.apply(&mut self.display);
+
+        let result = &mut self.result;
+        result.left.copy_from_slice(&self.display[..len]);
+        result.right.copy_from_slice(&self.display[len..]);
+
+        let (mut ll, mut rr, mut lr) = (0.0f32, 0.0f32, 0.0f32);
+        for (&l, &r) in left.iter().zip(right) {
+            ll += l * l;
+            rr += r * r;
+            lr += l * r;
+        }
+        result.correlation = if ll > 1e-10 && rr > 1e-10 {
+            (lr / (ll * rr).sqrt()).clamp(-1.0, 1.0)
+        } else {
+            0.0
+        };
+        result.balance = if ll + rr > 1e-10 {
+            (rr - ll) / (rr + ll)
+        } else {
+            0.0
+        };
+        // Mean squares of (L±R)/2 follow from the same three sums
+        result.mid = ((ll + rr + 2.0 * lr) / (4.0 * n as f32)).max(0.0).sqrt();
+        result.side = ((ll + rr - 2.0 * lr) / (4.0 * n as f32)).max(0.0).sqrt();
+
+        let start = n.saturating_sub(GONIOMETER_POINTS);
+        result.goniometer.clear();
+        result
+            .goniometer
+            .extend(left[start..].iter().zip(&right[start..]).map(|(&l, &r)| {
+                [
+                    (r - l) * std::f32::consts::FRAC_1_SQRT_2,
+                    (l + r) * std::f32::consts::FRAC_1_SQRT_2,
+                ]
+            }));
+
+        Some(&self.result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    fn sine(freq: f32, amplitude: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| amplitude * (TAU * freq * i as f32 / 48000.0).sin())
+            .collect()
+    }
+
+    fn analyze(left: &[f32], right: &[f32]) -> StereoResult {
+        let mut analyzer = StereoAnalyzer::new(2048, 48000);
+        analyzer.analyze(left, right).unwrap().clone()
+    }
+
+    #[test]
+    fn test_mono_signal() {
+        let s = sine(440.0, 0.5, 2048);
+        let result = analyze(&s, &s);
+        assert!((result.correlation - 1.0).abs() < 1e-4);
+        assert!(result.balance.abs() < 1e-4);
+        assert!(result.side < 1e-4);
+        assert!((result.mid - 0.5 / 2f32.sqrt()).abs() < 0.01);
+        assert_eq!(result.left, result.right);
+        assert_eq!(result.goniometer.len(), GONIOMETER_POINTS);
+        assert!(result.goniometer.iter().all(|p| p[0].abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_out_of_phase_and_one_sided() {
+        let s = sine(440.0, 0.5, 2048);
+        let inverted: Vec<f32> = s.iter().map(|x| -x).collect();
+        let result = analyze(&s, &inverted);
+        assert!((result.correlation + 1.0).abs() < 1e-4);
+        assert!(result.mid < 1e-4);
+
+        let silence = vec![0.0; 2048];
+        let result = analyze(&s, &silence);
+        assert!((result.balance + 1.0).abs() < 1e-4);
+        assert_eq!(result.correlation, 0.0);
+        // Hard left leans the goniometer trace left of vertical
+        let peak = result
+            .goniometer
+            .iter()
+            .max_by(|a, b| a[1].total_cmp(&b[1]))
+            .unwrap();
+        assert!(peak[0] < 0.0);
+    }
+
+    #[test]
+    fn test_unrelated_channels_and_panned_spectra() {
+        let mut rng = fastrand::Rng::with_seed(11);
+        let a: Vec<f32> = (0..2048).map(|_| rng.f32() - 0.5).collect();
+        let b: Vec<f32> = (0..2048).map(|_| rng.f32() - 0.5).collect();
+        assert!(analyze(&a, &b).correlation.abs() < 0.1);
+
+        // Louder on the right, and the spectra show it
+        let result = analyze(&sine(1000.0, 0.2, 2048), &sine(1000.0, 0.8, 2048));
+        assert!(result.balance > 0.8);
+        let peak = |s: &[f32]| s.iter().cloned().fold(0.0f32, f32::max);
+        assert!((peak(&result.right) - 1.0).abs() < 1e-4);
+        assert!((peak(&result.left) - 0.25).abs() < 0.01);
+    }
+}
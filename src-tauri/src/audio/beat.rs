@@ -1,80 +1,659 @@
 use std::collections::VecDeque;
+use std::ops::Range;
 
-const HISTORY_SIZE: usize = 60; // ~1 second at 60fps
-const COOLDOWN_FRAMES: usize = 6; // ~100ms at 60fps
+use serde::{Deserialize, Serialize};
+
+use super::tempo::TempoEstimator;
+use super::types::{OnsetBand, OnsetBands};
+
+/// Energy history the adaptive thresholds are taken over, and how much of
+/// it is needed before anything triggers.
+const HISTORY_SECS: f64 = 1.0;
+const MIN_HISTORY_SECS: f64 = 0.15;
+/// Highest frame rate `AudioConfig` allows; histories are sized for it.
+const MAX_FRAME_RATE: f64 = 120.0;
+const COOLDOWN_SECS: f64 = 0.1;
+/// Shorter, for fast hat patterns.
+const HAT_COOLDOWN_SECS: f64 = 0.05;
 const BPM_HISTORY: usize = 20;
+/// Range a manual BPM lock is clamped to.
+pub const MIN_LOCK_BPM: f32 = 30.0;
+pub const MAX_LOCK_BPM: f32 = 300.0;
+/// Taps averaged for tap tempo, and the pause after which tapping starts
+/// over.
+const TAP_HISTORY: usize = 8;
+const TAP_TIMEOUT_SECS: f64 = 2.0;
+/// Beats of accents kept for meter detection, and how many are needed
+/// before the meter is judged.
+const METER_HISTORY: usize = 48;
+const METER_MIN_BEATS: usize = 12;
+/// Triple grouping must stand out this much more than quadruple to win, so
+/// 4/4 stays the default on ambiguous material.
+const TRIPLE_MARGIN: f32 = 1.25;
+/// Least downbeat contrast, in standard deviations, that counts as a meter.
+const METER_MIN_CONTRAST: f32 = 0.5;
+/// Consecutive beats a new meter or downbeat must win before it's adopted.
+const METER_SWITCH_BEATS: usize = 4;
+/// Triple groupings of beats at least this fast are the eighths of 6/8.
+const COMPOUND_MIN_BPM: f64 = 150.0;
+/// Bars a new bar's mean onset strength is compared against, and the
+/// ratio either way that counts as a new section.
+const SECTION_CONTEXT_BARS: usize = 4;
+const SECTION_CHANGE_RATIO: f32 = 2.0;
+/// Detected beats further than this fraction of a period from the grid
+/// are ignored by the phase tracker.
+const PHASE_LOCK_WINDOW: f64 = 0.2;
+/// Fraction of a detected beat's timing error the grid moves by.
+const PHASE_GAIN: f64 = 0.25;
+/// Tempo confidence below which the median onset interval is reported
+/// instead.
+const MIN_TEMPO_CONFIDENCE: f32 = 0.2;
+/// Compression applied to spectrum values before taking the onset-strength
+/// flux, so quiet partials count as well as the loudest bins.
+const FLUX_COMPRESSION: f32 = 100.0;
+/// Rise in band energy below which nothing counts as a hit.
+const MIN_ONSET_RISE: f32 = 1e-6;
+
+/// One band's onset in a frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Onset {
+    pub detected: bool,
+    /// How far the rise in the band's energy cleared its threshold, 0..1;
+    /// 0 when nothing was detected.
+    pub strength: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Onsets {
+    pub kick: Onset,
+    pub snare: Onset,
+    pub hat: Onset,
+}
+
+/// Mean plus `sensitivity` standard deviations of a value over the last
+/// [`HISTORY_SECS`], with a cooldown between triggers. Windows are measured
+/// on frame timestamps, so they mean the same at any frame rate.
+struct AdaptiveThreshold {
+    history: VecDeque<(f64, f32)>,
+    cooldown: f64,
+    last_trigger: Option<f64>,
+}
+
+impl AdaptiveThreshold {
+    fn new(cooldown: f64) -> Self {
+        Self {
+            history: VecDeque::with_capacity((HISTORY_SECS * MAX_FRAME_RATE) as usize + 2),
+            cooldown,
+            last_trigger: None,
+        }
+    }
+
+    /// Adds `value` at `timestamp` and returns the threshold over the
+    /// window, or `None` while there's too little history.
+    fn push(&mut self, timestamp: f64, value: f32, sensitivity: f32) -> Option<f32> {
+        // Time went backwards: a seek, start over
+        if self.history.back().is_some_and(|&(t, _)| timestamp < t) {
+            self.history.clear();
+            self.last_trigger = None;
+        }
+        while self
+            .history
+            .front()
+            .is_some_and(|&(t, _)| timestamp - t >= HISTORY_SECS - 1e-9)
+        {
+            self.history.pop_front();
+        }
+        self.history.push_back((timestamp, value));
+        let (oldest, _) = self.history[0];
+        if timestamp - oldest < MIN_HISTORY_SECS - 1e-9 {
+            return None;
+        }
+
+        let n = self.history.len() as f32;
+        let mean = self.history.iter().map(|h| h.1).sum::<f32>() / n;
+        let variance = self.history.iter().map(|h| (h.1 - mean) * (h.1 - mean)).sum::<f32>() / n;
+        Some(mean + sensitivity * variance.sqrt())
+    }
+
+    /// Whether the cooldown has run out at `timestamp`; if so, starts it
+    /// again.
+    fn trigger(&mut self, timestamp: f64) -> bool {
+        if self
+            .last_trigger
+            .is_some_and(|last| timestamp - last < self.cooldown - 1e-9)
+        {
+            return false;
+        }
+        self.last_trigger = Some(timestamp);
+        true
+    }
+}
+
+/// Adaptive-threshold onset detector over one frequency band. Listens for
+/// rises in the band's energy, so a ringing kick or an open hat doesn't
+/// keep retriggering.
+struct BandOnsetDetector {
+    bins: Range<usize>,
+    prev_energy: f32,
+    threshold: AdaptiveThreshold,
+}
+
+impl BandOnsetDetector {
+    fn new(band: OnsetBand, bin_hz: f32, cooldown: f64) -> Self {
+        // Bin 0 is DC, never part of a hit
+        let low = ((band.low_hz / bin_hz).round() as usize).max(1);
+        let high = ((band.high_hz / bin_hz).round() as usize).max(low + 1);
+        Self {
+            bins: low..high,
+            prev_energy: 0.0,
+            threshold: AdaptiveThreshold::new(cooldown),
+        }
+    }
+
+    fn detect(&mut self, spectrum: &[f32], timestamp: f64, sensitivity: f32) -> Onset {
+        let end = self.bins.end.min(spectrum.len());
+        let start = self.bins.start.min(end);
+        let energy = if end > start {
+            spectrum[start..end].iter().map(|&s| s * s).sum::<f32>() / (end - start) as f32
+        } else {
+            0.0
+        };
+        let rise = (energy - self.prev_energy).max(0.0);
+        self.prev_energy = energy;
+
+        match self.threshold.push(timestamp, rise, sensitivity) {
+            Some(threshold)
+                if rise > threshold && rise > MIN_ONSET_RISE && self.threshold.trigger(timestamp) =>
+            {
+                Onset {
+                    detected: true,
+                    strength: ((rise - threshold) / rise).clamp(0.0, 1.0),
+                }
+            }
+            _ => Onset::default(),
+        }
+    }
+}
+
+/// Meter the bar counter runs in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeSignature {
+    #[default]
+    #[serde(rename = "4/4")]
+    FourFour,
+    #[serde(rename = "3/4")]
+    ThreeFour,
+    /// Tracked beats are the eighth notes, six to the bar.
+    #[serde(rename = "6/8")]
+    SixEight,
+}
+
+impl TimeSignature {
+    /// Tracked beats per bar.
+    pub fn beats_per_bar(self) -> u64 {
+        match self {
+            Self::FourFour => 4,
+            Self::ThreeFour => 3,
+            Self::SixEight => 6,
+        }
+    }
+}
+
+impl std::fmt::Display for TimeSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::FourFour => "4/4",
+            Self::ThreeFour => "3/4",
+            Self::SixEight => "6/8",
+        })
+    }
+}
+
+/// Position of a frame on the beat grid, for animating ahead of the beat
+/// rather than reacting after it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BeatPosition {
+    /// Progress from the last beat to the next, 0..1.
+    pub phase: f32,
+    /// Predicted time of the next beat, on the frame timestamp clock.
+    pub next_beat: f64,
+    /// Beats into the bar, e.g. 2.5 is halfway through the third beat.
+    pub bar_position: f32,
+    /// Bars since tracking started, counted at each downbeat.
+    pub bar: u64,
+    /// Beat within the bar, 0 on the downbeat.
+    pub beat_in_bar: u32,
+    /// The bar's first beat ticked in this frame.
+    pub downbeat: bool,
+    pub time_signature: TimeSignature,
+    /// Bar within the current 16-bar phrase, 0..16.
+    pub bar_in_phrase: u32,
+    /// On the downbeat that starts a phrase: 16 at a 16-bar boundary, 8 at
+    /// the halfway 8-bar one.
+    pub phrase_boundary: Option<u32>,
+}
+
+/// Finds the meter and the downbeat from how strongly each beat is hit:
+/// the grouping whose first beat stands out most from the others wins.
+#[derive(Default)]
+struct MeterDetector {
+    /// Strongest onset near each finished beat, by beat index.
+    accents: VecDeque<(u64, f32)>,
+    pending: Option<(u64, f32)>,
+    signature: TimeSignature,
+    /// Beat indices `i` with `i % beats_per_bar == downbeat_offset` are
+    /// downbeats.
+    downbeat_offset: u64,
+    candidate: Option<(TimeSignature, u64)>,
+    streak: usize,
+}
+
+impl MeterDetector {
+    /// Folds a frame's onset strength into the beat it's nearest to.
+    fn add(&mut self, beat_index: u64, strength: f32, bpm: f64) {
+        match self.pending {
+            Some((i, accent)) if i == beat_index => {
+                self.pending = Some((i, accent.max(strength)));
+            }
+            Some((i, accent)) if i < beat_index => {
+                if self.accents.len() >= METER_HISTORY {
+                    self.accents.pop_front();
+                }
+                self.accents.push_back((i, accent));
+                self.pending = Some((beat_index, strength));
+                self.evaluate(bpm);
+            }
+            _ => self.pending = Some((beat_index, strength)),
+        }
+    }
+
+    fn evaluate(&mut self, bpm: f64) {
+        if self.accents.len() < METER_MIN_BEATS {
+            return;
+        }
+        let (triple, triple_offset) = self.grouping(3);
+        let (quadruple, quadruple_offset) = self.grouping(4);
+
+        let found = if triple > quadruple * TRIPLE_MARGIN && triple > METER_MIN_CONTRAST {
+            if bpm >= COMPOUND_MIN_BPM {
+                (TimeSignature::SixEight, self.grouping(6).1)
+            } else {
+                (TimeSignature::ThreeFour, triple_offset)
+            }
+        } else if quadruple > METER_MIN_CONTRAST {
+            (TimeSignature::FourFour, quadruple_offset)
+        } else {
+            // No clear accent: stay put
+            (self.signature, self.downbeat_offset)
+        };
+
+        if found == (self.signature, self.downbeat_offset) {
+            self.candidate = None;
+        } else if self.candidate == Some(found) {
+            self.streak += 1;
+            if self.streak >= METER_SWITCH_BEATS {
+                (self.signature, self.downbeat_offset) = found;
+                self.candidate = None;
+            }
+        } else {
+            self.candidate = Some(found);
+            self.streak = 1;
+        }
+    }
+
+    /// How far the strongest position in groups of `beats` stands out, in
+    /// standard deviations of all accents, and which position that is.
+    fn grouping(&self, beats: u64) -> (f32, u64) {
+        let mut sums = [0.0f32; 6];
+        let mut counts = [0u32; 6];
+        for &(i, accent) in &self.accents {
+            sums[(i % beats) as usize] += accent;
+            counts[(i % beats) as usize] += 1;
+        }
+        let n = self.accents.len() as f32;
+        let mean = self.accents.iter().map(|a| a.1).sum::<f32>() / n;
+        let std = (self.accents.iter().map(|a| (a.1 - mean) * (a.1 - mean)).sum::<f32>() / n).sqrt();
+        if std < 1e-9 {
+            return (0.0, 0);
+        }
+
+        let means: Vec<f32> = (0..beats as usize)
+            .map(|p| sums[p] / counts[p].max(1) as f32)
+            .collect();
+        let (best, &top) = means
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap_or((0, &0.0));
+        let others = (means.iter().sum::<f32>() - top) / (beats - 1) as f32;
+        ((top - others) / std, best as u64)
+    }
+}
+
+/// Phase-locked loop over the tempo: predicted beats tick on their own,
+/// and detected beats near a prediction pull the grid toward them. Beats
+/// are grouped into bars and phrases as they tick.
+#[derive(Default)]
+struct BeatTracker {
+    period: f64,
+    next_beat: Option<f64>,
+    /// Beats since the grid was started.
+    beat_count: u64,
+    meter: MeterDetector,
+    bar: Option<u64>,
+    beat_in_bar: u32,
+    downbeat: bool,
+    /// Bar the current phrase started on.
+    phrase_start: u64,
+    phrase_boundary: Option<u32>,
+    /// Onset strength summed over the current bar, and frames in it.
+    bar_strength: (f32, u32),
+    /// Mean onset strength of the last few bars, newest last.
+    recent_bars: VecDeque<f32>,
+    position: Option<BeatPosition>,
+    /// Run on the clock alone, ignoring detected beats.
+    locked: bool,
+    /// A beat ticked in the latest update.
+    ticked: bool,
+}
+
+impl BeatTracker {
+    fn update(&mut self, timestamp: f64, beat: bool, bpm: f32, strength: f32) {
+        self.ticked = false;
+        if bpm <= 0.0 {
+            return;
+        }
+        self.period = 60.0 / bpm as f64;
+        let period = self.period;
+        self.downbeat = false;
+        self.phrase_boundary = None;
+
+        let Some(mut next) = self.next_beat else {
+            // A locked grid doesn't wait for the music to start it
+            if beat || self.locked {
+                self.ticked = self.locked;
+                self.next_beat = Some(timestamp + period);
+                self.position = Some(self.position_at(timestamp, timestamp + period));
+            }
+            return;
+        };
+
+        // Time went backwards: start the grid over at the next beat
+        if timestamp < next - 2.0 * period {
+            *self = Self {
+                locked: self.locked,
+                ..Self::default()
+            };
+            return self.update(timestamp, beat, bpm, strength);
+        }
+
+        while timestamp >= next {
+            next += period;
+            self.tick();
+        }
+
+        if beat && !self.locked {
+            let since_prev = timestamp - (next - period);
+            let until_next = next - timestamp;
+            let window = PHASE_LOCK_WINDOW * period;
+            if since_prev <= until_next {
+                // Late for the beat just passed: push the grid back
+                if since_prev < window {
+                    next += PHASE_GAIN * since_prev;
+                }
+            } else if until_next < window {
+                // Early for the coming beat: it has arrived, pull the grid in
+                self.tick();
+                next += period - PHASE_GAIN * until_next;
+            }
+        }
+
+        self.next_beat = Some(next);
+        let position = self.position_at(timestamp, next);
+        let nearest = self.beat_count + u64::from(position.phase >= 0.5);
+        self.meter.add(nearest, strength, bpm as f64);
+        self.bar_strength.0 += strength;
+        self.bar_strength.1 += 1;
+        self.position = Some(position);
+    }
+
+    /// Moves the grid so a beat falls at `timestamp`.
+    fn align(&mut self, timestamp: f64, bpm: f32) {
+        self.period = 60.0 / bpm as f64;
+        self.next_beat = Some(timestamp + self.period);
+    }
+
+    /// Shifts the grid later by `secs`, or earlier if negative.
+    fn nudge(&mut self, secs: f64) {
+        if let Some(next) = self.next_beat.as_mut() {
+            *next += secs;
+        }
+    }
+
+    fn tick(&mut self) {
+        self.ticked = true;
+        self.beat_count += 1;
+        let beats = self.meter.signature.beats_per_bar();
+        let offset = self.meter.downbeat_offset % beats;
+        self.beat_in_bar = ((self.beat_count + beats - offset) % beats) as u32;
+        if self.beat_in_bar != 0 {
+            return;
+        }
+
+        self.downbeat = true;
+        let bar = self.bar.map_or(0, |b| b + 1);
+        self.bar = Some(bar);
+
+        // A big change in how hard the music hits starts a new phrase
+        let (sum, frames) = std::mem::take(&mut self.bar_strength);
+        let energy = sum / frames.max(1) as f32;
+        if self.recent_bars.len() == SECTION_CONTEXT_BARS {
+            let before = self.recent_bars.iter().sum::<f32>() / SECTION_CONTEXT_BARS as f32;
+            if before > 0.0 && !(1.0 / SECTION_CHANGE_RATIO..=SECTION_CHANGE_RATIO).contains(&(energy / before)) {
+                self.phrase_start = bar;
+                self.recent_bars.clear();
+            }
+        }
+        if self.recent_bars.len() == SECTION_CONTEXT_BARS {
+            self.recent_bars.pop_front();
+        }
+        self.recent_bars.push_back(energy);
+
+        let into_phrase = bar - self.phrase_start;
+        if into_phrase.is_multiple_of(8) {
+            self.phrase_boundary = Some(if into_phrase.is_multiple_of(16) { 16 } else { 8 });
+        }
+    }
+
+    fn position_at(&self, timestamp: f64, next: f64) -> BeatPosition {
+        let phase = (1.0 - (next - timestamp) / self.period).clamp(0.0, 1.0) as f32;
+        let bar = self.bar.unwrap_or(0);
+        BeatPosition {
+            phase,
+            next_beat: next,
+            bar_position: self.beat_in_bar as f32 + phase,
+            bar,
+            beat_in_bar: self.beat_in_bar,
+            downbeat: self.downbeat,
+            time_signature: self.meter.signature,
+            bar_in_phrase: ((bar - self.phrase_start.min(bar)) % 16) as u32,
+            phrase_boundary: self.phrase_boundary,
+        }
+    }
+}
+
+/// Tempo from the spacing of taps, averaged over the last few.
+#[derive(Debug, Default)]
+pub struct TapTempo {
+    taps: VecDeque<f64>,
+}
+
+impl TapTempo {
+    /// Records a tap at `timestamp` seconds. Returns the tapped tempo once
+    /// there are two taps; a long pause starts over.
+    pub fn tap(&mut self, timestamp: f64) -> Option<f32> {
+        if self
+            .taps
+            .back()
+            .is_some_and(|&last| timestamp < last || timestamp - last > TAP_TIMEOUT_SECS)
+        {
+            self.taps.clear();
+        }
+        if self.taps.len() == TAP_HISTORY {
+            self.taps.pop_front();
+        }
+        self.taps.push_back(timestamp);
+
+        let (&first, &last) = (self.taps.front()?, self.taps.back()?);
+        if self.taps.len() < 2 || last <= first {
+            return None;
+        }
+        let bpm = 60.0 * (self.taps.len() - 1) as f64 / (last - first);
+        Some((bpm as f32).clamp(MIN_LOCK_BPM, MAX_LOCK_BPM))
+    }
+}
 
 pub struct BeatDetector {
-    energy_history: VecDeque<f32>,
-    cooldown_counter: usize,
+    bass: AdaptiveThreshold,
     beat_timestamps: VecDeque<f64>,
     sensitivity: f32,
     current_bpm: f32,
+    /// Kick, snare and hat detectors; empty until [`Self::set_onset_bands`].
+    bands: Vec<BandOnsetDetector>,
+    onsets: Onsets,
+    /// Log-compressed spectrum of the previous frame, for onset strength.
+    prev_compressed: Vec<f32>,
+    tempo: TempoEstimator,
+    tracker: BeatTracker,
+    bpm_lock: Option<f32>,
 }
 
 impl BeatDetector {
     pub fn new(sensitivity: f32) -> Self {
         Self {
-            energy_history: VecDeque::with_capacity(HISTORY_SIZE + 1),
-            cooldown_counter: 0,
+            bass: AdaptiveThreshold::new(COOLDOWN_SECS),
             beat_timestamps: VecDeque::with_capacity(BPM_HISTORY + 1),
             sensitivity,
             current_bpm: 0.0,
+            bands: Vec::new(),
+            onsets: Onsets::default(),
+            prev_compressed: Vec::new(),
+            tempo: TempoEstimator::new(),
+            tracker: BeatTracker::default(),
+            bpm_lock: None,
+        }
+    }
+
+    /// Overrides the detected tempo with `bpm`, or goes back to detection
+    /// with `None`. While locked, beats come from the clock grid rather than
+    /// the audio, so they keep going through breakdowns.
+    pub fn set_bpm_lock(&mut self, bpm: Option<f32>) {
+        self.bpm_lock = bpm.map(|b| b.clamp(MIN_LOCK_BPM, MAX_LOCK_BPM));
+        self.tracker.locked = self.bpm_lock.is_some();
+    }
+
+    pub fn bpm_lock(&self) -> Option<f32> {
+        self.bpm_lock
+    }
+
+    /// Puts a beat on the grid at `timestamp`, as when tapping along.
+    pub fn align_beat(&mut self, timestamp: f64) {
+        let bpm = self.bpm();
+        if bpm > 0.0 {
+            self.tracker.align(timestamp, bpm);
         }
     }
 
-    #[allow(dead_code)]
+    /// Shifts the beat grid later by `secs`, or earlier if negative.
+    pub fn nudge_phase(&mut self, secs: f64) {
+        self.tracker.nudge(secs);
+    }
+
+    /// Sets up the per-drum onset detectors for spectra with bins `bin_hz`
+    /// apart.
+    pub fn set_onset_bands(&mut self, bands: &OnsetBands, bin_hz: f32) {
+        self.bands = vec![
+            BandOnsetDetector::new(bands.kick, bin_hz, COOLDOWN_SECS),
+            BandOnsetDetector::new(bands.snare, bin_hz, COOLDOWN_SECS),
+            BandOnsetDetector::new(bands.hat, bin_hz, HAT_COOLDOWN_SECS),
+        ];
+    }
+
+    /// Per-drum onsets from the latest [`Self::detect`].
+    pub fn onsets(&self) -> Onsets {
+        self.onsets
+    }
+
+    /// Confidence of the autocorrelation tempo, 0..1. Below
+    /// [`MIN_TEMPO_CONFIDENCE`] the reported BPM comes from onset intervals.
+    pub fn tempo_confidence(&self) -> f32 {
+        self.tempo.confidence()
+    }
+
+    /// Where the latest frame sits in the beat; `None` until a tempo and a
+    /// first beat are known.
+    pub fn position(&self) -> Option<BeatPosition> {
+        self.tracker.position
+    }
+
     pub fn set_sensitivity(&mut self, sensitivity: f32) {
         self.sensitivity = sensitivity.clamp(0.5, 2.0);
     }
 
+    /// Takes the normalized linear spectrum of a frame. Returns whether it
+    /// holds a beat and the current tempo; drum onsets are in
+    /// [`Self::onsets`].
     pub fn detect(&mut self, spectrum: &[f32], timestamp: f64) -> (bool, f32) {
+        self.onsets = Onsets::default();
         if spectrum.is_empty() {
-            return (false, self.current_bpm);
+            return (false, self.bpm());
         }
 
-        // Compute bass-weighted energy (lower 1/8 of spectrum)
-        let bass_end = (spectrum.len() / 8).max(1);
-        let bass_energy: f32 = spectrum[..bass_end]
-            .iter()
-            .map(|&s| s * s)
-            .sum::<f32>()
-            / bass_end as f32;
+        let strength = self.onset_strength(spectrum);
+        self.tempo.push(timestamp, strength);
 
-        // Add to history (O(1) with VecDeque)
-        if self.energy_history.len() >= HISTORY_SIZE {
-            self.energy_history.pop_front();
+        if let [kick, snare, hat] = self.bands.as_mut_slice() {
+            self.onsets = Onsets {
+                kick: kick.detect(spectrum, timestamp, self.sensitivity),
+                snare: snare.detect(spectrum, timestamp, self.sensitivity),
+                hat: hat.detect(spectrum, timestamp, self.sensitivity),
+            };
         }
-        self.energy_history.push_back(bass_energy);
 
-        // Need enough history for meaningful detection
-        if self.energy_history.len() < 10 {
-            return (false, self.current_bpm);
+        let is_beat = self.detect_bass_beat(spectrum, timestamp);
+        let bpm = self.bpm();
+        self.tracker.update(timestamp, is_beat, bpm, strength);
+        if self.bpm_lock.is_some() {
+            (self.tracker.ticked, bpm)
+        } else {
+            (is_beat, bpm)
         }
+    }
 
-        // Compute mean and standard deviation
-        let mean: f32 =
-            self.energy_history.iter().sum::<f32>() / self.energy_history.len() as f32;
-        let variance: f32 = self
-            .energy_history
+    /// Adaptive threshold on bass energy; also feeds the onset-interval tempo.
+    fn detect_bass_beat(&mut self, spectrum: &[f32], timestamp: f64) -> bool {
+        // Compute bass-weighted energy (lower 1/8 of spectrum)
+        let bass_end = (spectrum.len() / 8).max(1);
+        let bass_energy: f32 = spectrum[..bass_end]
             .iter()
-            .map(|&e| (e - mean) * (e - mean))
+            .map(|&s| s * s)
             .sum::<f32>()
-            / self.energy_history.len() as f32;
-        let stddev = variance.sqrt();
+            / bass_end as f32;
 
-        // Adaptive threshold
-        let threshold = mean + self.sensitivity * stddev;
+        let is_beat = match self.bass.push(timestamp, bass_energy, self.sensitivity) {
+            Some(threshold) => {
+                bass_energy > threshold && threshold > 0.001 && self.bass.trigger(timestamp)
+            }
+            None => false,
+        };
 
-        // Decrease cooldown
-        if self.cooldown_counter > 0 {
-            self.cooldown_counter -= 1;
+        if self.beat_timestamps.back().is_some_and(|&t| timestamp < t) {
+            self.beat_timestamps.clear();
         }
-
-        let is_beat = bass_energy > threshold && self.cooldown_counter == 0 && threshold > 0.001;
-
         if is_beat {
-            self.cooldown_counter = COOLDOWN_FRAMES;
             self.beat_timestamps.push_back(timestamp);
             if self.beat_timestamps.len() > BPM_HISTORY {
                 self.beat_timestamps.pop_front();
@@ -82,7 +661,33 @@ impl BeatDetector {
             self.update_bpm();
         }
 
-        (is_beat, self.current_bpm)
+        is_beat
+    }
+
+    /// The lock if set; otherwise the autocorrelation tempo when it's
+    /// confident, else the onset-interval one.
+    fn bpm(&self) -> f32 {
+        if let Some(bpm) = self.bpm_lock {
+            bpm
+        } else if self.tempo.confidence() >= MIN_TEMPO_CONFIDENCE {
+            self.tempo.bpm()
+        } else {
+            self.current_bpm
+        }
+    }
+
+    /// Summed rise of the log-compressed spectrum since the last frame.
+    fn onset_strength(&mut self, spectrum: &[f32]) -> f32 {
+        if self.prev_compressed.len() != spectrum.len() {
+            self.prev_compressed = vec![0.0; spectrum.len()];
+        }
+        let mut flux = 0.0;
+        for (prev, &s) in self.prev_compressed.iter_mut().zip(spectrum) {
+            let compressed = (1.0 + FLUX_COMPRESSION * s).ln();
+            flux += (compressed - *prev).max(0.0);
+            *prev = compressed;
+        }
+        flux / spectrum.len() as f32
     }
 
     fn update_bpm(&mut self) {
@@ -171,6 +776,237 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_phase_predicts_the_next_beat() {
+        // Same 120 BPM pattern as test_120_bpm_detection
+        let mut detector = BeatDetector::new(1.0);
+        let interval = 0.5;
+        let mut last_bar_beat = None;
+        for frame in 0..1200 {
+            let time = frame as f64 / 60.0;
+            let is_beat_frame = (time % interval) < (1.0 / 60.0);
+            let mut spectrum = vec![0.01f32; 256];
+            if is_beat_frame {
+                spectrum[..32].fill(1.0);
+            }
+            detector.detect(&spectrum, time);
+
+            // Give the tempo and the grid ten seconds to settle
+            if frame < 600 {
+                continue;
+            }
+            let position = detector.position().expect("tracking by now");
+            let next_actual = (time / interval).floor() * interval + interval;
+            assert!(
+                (position.next_beat - next_actual).abs() < 1.5 / 60.0,
+                "at {time:.3}: predicted {:.3}, actual {next_actual:.3}",
+                position.next_beat
+            );
+            let expected_phase = ((time % interval) / interval) as f32;
+            assert!(
+                (position.phase - expected_phase).abs() < 0.06,
+                "at {time:.3}: phase {} vs {expected_phase}",
+                position.phase
+            );
+
+            // Bar position steps through four beats; a beat may land a hair
+            // before the predicted one, so round. The pattern's float modulo
+            // sometimes marks two frames in a row, so step by frame count.
+            if frame % 30 == 0 {
+                let beat_in_bar = position.bar_position.round() as u32 % 4;
+                if let Some(prev) = last_bar_beat {
+                    assert_eq!(beat_in_bar, (prev + 1) % 4);
+                }
+                last_bar_beat = Some(beat_in_bar);
+            }
+        }
+    }
+
+    /// Runs `secs` of 60 fps frames with a bass hit every `frames_per_beat`,
+    /// cycling through `accents` for its level, and returns each frame's
+    /// position after the first `settle` seconds.
+    fn run_accents(frames_per_beat: usize, accents: &[f32], secs: usize, settle: usize) -> Vec<(usize, BeatPosition)> {
+        let mut detector = BeatDetector::new(1.0);
+        let mut positions = Vec::new();
+        for frame in 0..secs * 60 {
+            let mut spectrum = vec![0.01f32; 256];
+            if frame % frames_per_beat == 0 {
+                spectrum[..32].fill(accents[frame / frames_per_beat % accents.len()]);
+            }
+            detector.detect(&spectrum, frame as f64 / 60.0);
+            if frame >= settle * 60 {
+                positions.push((frame, detector.position().expect("tracking by now")));
+            }
+        }
+        positions
+    }
+
+    #[test]
+    fn test_four_four_downbeats_bars_and_phrases() {
+        // 120 BPM, beat 1 hit hardest and beat 3 a little harder than 2 and 4
+        let positions = run_accents(30, &[1.0, 0.4, 0.6, 0.4], 60, 15);
+        let mut last_bar = None;
+        let mut boundaries = Vec::new();
+        for (frame, position) in positions {
+            assert_eq!(position.time_signature, TimeSignature::FourFour);
+            if !position.downbeat {
+                continue;
+            }
+            // Downbeats land on the accented hit, give or take a frame
+            let off = frame % 120;
+            assert!(off <= 1 || off >= 119, "downbeat at frame {frame}");
+            if let Some(prev) = last_bar {
+                assert_eq!(position.bar, prev + 1);
+            }
+            last_bar = Some(position.bar);
+            if let Some(length) = position.phrase_boundary {
+                assert_eq!(position.bar_in_phrase % 8, 0);
+                boundaries.push((position.bar, length));
+            }
+        }
+        assert!(boundaries.len() >= 2, "{boundaries:?}");
+        for pair in boundaries.windows(2) {
+            assert_eq!(pair[1].0 - pair[0].0, 8, "{boundaries:?}");
+            assert_ne!(pair[1].1, pair[0].1, "{boundaries:?}");
+        }
+    }
+
+    #[test]
+    fn test_three_four_detected() {
+        let positions = run_accents(30, &[1.0, 0.4, 0.4], 40, 25);
+        for (frame, position) in positions {
+            assert_eq!(position.time_signature, TimeSignature::ThreeFour);
+            if position.downbeat {
+                let off = frame % 90;
+                assert!(off <= 1 || off >= 89, "downbeat at frame {frame}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_six_eight_detected() {
+        // Eighths at about 164 BPM, strong on 1 and medium on 4. Much
+        // faster and the tempo estimator reads the quarter-note level.
+        let positions = run_accents(22, &[1.0, 0.3, 0.3, 0.6, 0.3, 0.3], 40, 25);
+        for (frame, position) in positions {
+            assert_eq!(position.time_signature, TimeSignature::SixEight);
+            if position.downbeat {
+                let off = frame % 132;
+                assert!(off <= 1 || off >= 131, "downbeat at frame {frame}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_slow_pulse_uses_autocorrelation_tempo() {
+        // 50 BPM is outside what onset intervals are trusted for
+        let mut detector = BeatDetector::new(1.0);
+        let mut bpm = 0.0;
+        for frame in 0..1200 {
+            let time = frame as f64 / 60.0;
+            let mut spectrum = vec![0.01f32; 256];
+            if frame % 72 == 0 {
+                spectrum[..32].fill(1.0);
+            }
+            bpm = detector.detect(&spectrum, time).1;
+        }
+        assert_eq!(detector.current_bpm, 0.0);
+        assert!((bpm - 50.0).abs() < 1.0, "{bpm}");
+        assert!(detector.tempo_confidence() > 0.5);
+    }
+
+    #[test]
+    fn test_same_beats_at_any_frame_rate() {
+        // Decaying kicks at 100 BPM with hats between, sampled at each rate;
+        // hits land on frame boundaries at all three
+        let bin_hz = 48000.0 / 2048.0;
+        let run = |fps: usize| {
+            let mut detector = BeatDetector::new(1.0);
+            detector.set_onset_bands(&OnsetBands::default(), bin_hz);
+            let (mut beats, mut hats) = (Vec::new(), Vec::new());
+            let mut bpm = 0.0;
+            let period = fps * 6 / 10;
+            for frame in 0..12 * fps {
+                let time = frame as f64 / fps as f64;
+                let since_kick = (frame % period) as f64 / fps as f64;
+                let since_hat = ((frame + period / 2) % period) as f64 / fps as f64;
+                let mut spectrum = vec![0.01f32; 1024];
+                spectrum[..128].fill((-since_kick / 0.08).exp() as f32);
+                spectrum[300..640].fill(0.3 * (-since_hat / 0.03).exp() as f32);
+                let (beat, frame_bpm) = detector.detect(&spectrum, time);
+                if beat {
+                    beats.push((time * 1000.0).round() as u32);
+                }
+                if detector.onsets().hat.detected {
+                    hats.push((time * 1000.0).round() as u32);
+                }
+                bpm = frame_bpm;
+            }
+            (beats, hats, bpm)
+        };
+
+        let (beats, hats, bpm) = run(60);
+        assert!(beats.len() >= 18, "{beats:?}");
+        assert!(hats.len() >= 18, "{hats:?}");
+        assert!((bpm - 100.0).abs() < 1.0, "{bpm}");
+        for fps in [30, 120] {
+            let (other_beats, other_hats, other_bpm) = run(fps);
+            assert_eq!(other_beats, beats, "{fps} fps");
+            assert_eq!(other_hats, hats, "{fps} fps");
+            assert!((other_bpm - bpm).abs() < 0.5, "{fps} fps: {other_bpm} vs {bpm}");
+        }
+    }
+
+    #[test]
+    fn test_tap_tempo() {
+        let mut taps = TapTempo::default();
+        assert_eq!(taps.tap(10.0), None);
+        let mut bpm = None;
+        for t in [10.5, 11.0, 11.5] {
+            bpm = taps.tap(t);
+        }
+        assert!((bpm.unwrap() - 120.0).abs() < 0.01, "{bpm:?}");
+
+        // Pausing starts over
+        assert_eq!(taps.tap(20.0), None);
+        assert!((taps.tap(20.4).unwrap() - 150.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_locked_grid_keeps_time_through_silence() {
+        let mut detector = BeatDetector::new(1.0);
+        detector.set_bpm_lock(Some(128.0));
+        let period = 60.0 / 128.0f64;
+        let spectrum = vec![0.01f32; 256];
+        let mut beats = Vec::new();
+        for frame in 0..600 {
+            let time = frame as f64 / 60.0;
+            if frame == 0 {
+                detector.align_beat(time);
+            }
+            let (beat, bpm) = detector.detect(&spectrum, time);
+            assert_eq!(bpm, 128.0);
+            if beat {
+                beats.push(time);
+            }
+        }
+        // One per period after the aligned beat, on the first frame past it
+        assert_eq!(beats.len(), (10.0 / period) as usize);
+        for (i, &t) in beats.iter().enumerate() {
+            let expected = (i + 1) as f64 * period;
+            assert!(t >= expected && t - expected < 1.0 / 60.0, "{i}: {t}");
+        }
+
+        let next = detector.position().unwrap().next_beat;
+        detector.nudge_phase(0.02);
+        assert!((detector.position().unwrap().next_beat - next).abs() < 1e-9);
+        detector.detect(&spectrum, 10.0);
+        assert!((detector.position().unwrap().next_beat - next - 0.02).abs() < 1e-9);
+
+        detector.set_bpm_lock(None);
+        assert_eq!(detector.bpm_lock(), None);
+    }
+
     #[test]
     fn test_silence_no_beats() {
         let mut detector = BeatDetector::new(1.0);
@@ -181,4 +1017,59 @@ mod tests {
             assert!(!beat, "Should not detect beats in silence");
         }
     }
+
+    #[test]
+    fn test_drum_bands_trigger_independently() {
+        // 48 kHz / 2048-point FFT: kick, snare and hat hits each fill their
+        // own band over a quiet floor
+        let bin_hz = 48000.0 / 2048.0;
+        let mut detector = BeatDetector::new(1.0);
+        detector.set_onset_bands(&OnsetBands::default(), bin_hz);
+        let bins = |lo: f32, hi: f32| (lo / bin_hz) as usize..(hi / bin_hz) as usize;
+
+        let (mut kicks, mut snares, mut hats) = (0, 0, 0);
+        let mut wrong = 0;
+        // 120 BPM over 10 s: kick on every beat, snare on 2 and 4, hats on
+        // the off-beat eighths
+        for frame in 0..600 {
+            let step = frame % 15; // 15 frames per eighth note
+            let eighth = frame / 15;
+            let is_kick = step == 0 && eighth % 2 == 0;
+            let is_snare = step == 0 && eighth % 4 == 2;
+            let is_hat = step == 0 && eighth % 2 == 1;
+
+            let mut spectrum = vec![0.01f32; 1024];
+            if is_kick {
+                spectrum[bins(50.0, 120.0)].fill(1.0);
+            }
+            if is_snare {
+                spectrum[bins(300.0, 2000.0)].fill(0.6);
+            }
+            if is_hat {
+                spectrum[bins(7000.0, 15000.0)].fill(0.3);
+            }
+
+            detector.detect(&spectrum, frame as f64 / 60.0);
+            let onsets = detector.onsets();
+            for (onset, expected, count) in [
+                (onsets.kick, is_kick, &mut kicks),
+                (onsets.snare, is_snare, &mut snares),
+                (onsets.hat, is_hat, &mut hats),
+            ] {
+                if onset.detected {
+                    assert!(onset.strength > 0.0);
+                    if expected {
+                        *count += 1;
+                    } else {
+                        wrong += 1;
+                    }
+                }
+            }
+        }
+
+        assert!(kicks >= 15, "{kicks} kicks");
+        assert!(snares >= 7, "{snares} snares");
+        assert!(hats >= 15, "{hats} hats");
+        assert_eq!(wrong, 0);
+    }
 }
@@ -1,6 +1,6 @@
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{Device, SampleRate, Stream, StreamConfig};
-use ringbuf::traits::Producer;
+use ringbuf::traits::{Observer, Producer};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
@@ -86,14 +86,15 @@ pub fn start_capture(
                 if !running_clone.load(Ordering::Relaxed) {
                     return;
                 }
-                // Downmix to mono if stereo
-                if channels == 1 {
-                    let _ = producer.push_slice(data);
-                } else {
-                    for chunk in data.chunks(channels) {
-                        let mono: f32 = chunk.iter().sum::<f32>() / channels as f32;
-                        let _ = producer.push_slice(&[mono]);
+                // Interleaved stereo: mono input goes to both sides, and
+                // only the first two channels of wider devices are kept
+                for frame in data.chunks_exact(channels) {
+                    if producer.vacant_len() < 2 {
+                        break;
                     }
+                    let left = frame[0];
+                    let right = if channels == 1 { left } else { frame[1] };
+                    let _ = producer.push_slice(&[left, right]);
                 }
             },
             |err| {
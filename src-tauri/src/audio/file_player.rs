@@ -1,32 +1,47 @@
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::thread::JoinHandle;
-
-use ringbuf::traits::Producer;
+#[cfg(feature = "device")]
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+};
+
+#[cfg(feature = "device")]
+use ringbuf::traits::{Observer, Producer};
 use symphonia::core::audio::SampleBuffer;
-use symphonia::core::codecs::DecoderOptions;
-use symphonia::core::formats::FormatOptions;
+use symphonia::core::codecs::{Decoder, DecoderOptions};
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
 use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
+use symphonia::core::units::{Time, TimeBase};
 
+#[cfg(feature = "device")]
+use super::output;
+#[cfg(feature = "device")]
+use super::ring_buffer::AudioRingBuffer;
 use crate::error::AppError;
 
 pub struct FileInfo {
+    /// Rate of the samples fed to analysis (the output device rate).
     pub sample_rate: u32,
     pub duration_secs: f64,
 }
 
-pub struct FilePlayer;
+/// An opened audio file: demuxer and decoder for its default track.
+pub struct AudioSource {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    time_base: Option<TimeBase>,
+    sample_rate: u32,
+    duration_secs: f64,
+    /// Decoded frames still to drop after an accurate seek.
+    skip_frames: usize,
+}
 
-impl FilePlayer {
-    pub fn play_file(
-        path: &str,
-        mut producer: impl Producer<Item = f32> + Send + 'static,
-        running: Arc<AtomicBool>,
-        paused: Arc<AtomicBool>,
-        target_fps: u32,
-    ) -> Result<(JoinHandle<()>, FileInfo), AppError> {
+impl AudioSource {
+    pub fn open(path: &str) -> Result<Self, AppError> {
         let file = std::fs::File::open(path)
             .map_err(|e| AppError::Audio(format!("Failed to open file: {e}")))?;
 
@@ -46,7 +61,7 @@ impl FilePlayer {
             )
             .map_err(|e| AppError::Audio(format!("Failed to probe file: {e}")))?;
 
-        let mut format = probed.format;
+        let format = probed.format;
 
         let track = format
             .default_track()
@@ -57,128 +72,636 @@ impl FilePlayer {
             .sample_rate
             .unwrap_or(44100);
 
-        let channels = track
-            .codec_params
-            .channels
-            .map(|c| c.count())
-            .unwrap_or(2);
-
         let duration_secs = track
             .codec_params
             .n_frames
             .map(|n| n as f64 / sample_rate as f64)
             .unwrap_or(0.0);
 
-        let track_id = track.id;
-        let codec_params = track.codec_params.clone();
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .map_err(|e| AppError::Audio(format!("Failed to create decoder: {e}")))?;
 
-        let file_info = FileInfo {
+        Ok(Self {
+            track_id: track.id,
+            time_base: track.codec_params.time_base,
+            format,
+            decoder,
             sample_rate,
             duration_secs,
-        };
+            skip_frames: 0,
+        })
+    }
 
-        let handle = std::thread::spawn(move || {
-            let mut decoder = match symphonia::default::get_codecs()
-                .make(&codec_params, &DecoderOptions::default())
-            {
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn duration_secs(&self) -> f64 {
+        self.duration_secs
+    }
+
+    /// Decodes the next packet and appends it to `out` as interleaved stereo.
+    /// Returns false at the end of the stream.
+    pub fn read_stereo(&mut self, out: &mut Vec<f32>) -> bool {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(p) => p,
+                // EOF, or an unrecoverable demux error — either way we're done
+                Err(_) => return false,
+            };
+
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let decoded = match self.decoder.decode(&packet) {
                 Ok(d) => d,
-                Err(e) => {
-                    eprintln!("Failed to create decoder: {e}");
-                    return;
-                }
+                Err(_) => continue,
             };
 
-            // Calculate pacing: how many samples to produce per frame
-            let samples_per_frame = sample_rate as usize / target_fps.max(1) as usize;
-            let pace_interval = std::time::Duration::from_micros(1_000_000 / target_fps.max(1) as u64);
+            let spec = *decoded.spec();
+            let num_frames = decoded.frames();
+            let ch = spec.channels.count().max(1);
+
+            let mut sample_buf = SampleBuffer::<f32>::new(num_frames as u64, spec);
+            sample_buf.copy_interleaved_ref(decoded);
+
+            // Accurate seeks land on a packet boundary before the target;
+            // drop the lead-in so playback resumes exactly where asked
+            let skip = self.skip_frames.min(num_frames);
+            self.skip_frames -= skip;
+
+            to_stereo(&sample_buf.samples()[skip * ch..], ch, out);
+            return true;
+        }
+    }
+
+    /// Seeks to `position_secs` and resets the decoder.
+    pub fn seek(&mut self, position_secs: f64) -> Result<(), AppError> {
+        let seeked = self
+            .format
+            .seek(
+                SeekMode::Accurate,
+                SeekTo::Time {
+                    time: Time::from(position_secs),
+                    track_id: Some(self.track_id),
+                },
+            )
+            .map_err(|e| AppError::Audio(format!("Failed to seek: {e}")))?;
+
+        self.decoder.reset();
+
+        let lead_in = seeked.required_ts.saturating_sub(seeked.actual_ts);
+        self.skip_frames = match self.time_base {
+            Some(tb) => {
+                let t = tb.calc_time(lead_in);
+                ((t.seconds as f64 + t.frac) * self.sample_rate as f64).round() as usize
+            }
+            None => lead_in as usize,
+        };
+        Ok(())
+    }
+}
+
+/// A track handed to the player by whoever owns the play queue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueuedTrack {
+    pub id: u64,
+    pub path: String,
+}
+
+/// Supplies tracks to the decode thread and hears back about playback.
+/// Called from the decode thread, never from the audio callback.
+pub trait TrackQueue: Send + 'static {
+    /// Track to play straight after `current`, or `None` to stop after it.
+    fn next_after(&mut self, current: &QueuedTrack) -> Option<QueuedTrack>;
+
+    /// `track` has become audible.
+    fn started(&mut self, track: &QueuedTrack, duration_secs: f64);
+
+    /// The last queued track has been played out in full (not called on stop).
+    fn finished(&mut self, track: &QueuedTrack, position_secs: f64);
+}
+
+#[cfg(feature = "device")]
+enum PlaybackRequest {
+    Seek(f64),
+    Jump(QueuedTrack),
+}
+
+#[cfg(feature = "device")]
+/// A track's place in the output timeline.
+#[derive(Clone)]
+struct TimelineEntry {
+    start_frame: u64,
+    track: QueuedTrack,
+    duration_secs: f64,
+}
+
+#[cfg(feature = "device")]
+/// Seek/position state shared by the command layer, the decode thread and
+/// the output callback.
+pub struct PlaybackControl {
+    output_rate: u32,
+    request: Mutex<Option<PlaybackRequest>>,
+    /// Output frames played since playback began (moved by seeks).
+    played_frames: AtomicU64,
+    /// Set by the decode thread on seek; the output callback discards buffered
+    /// audio, moves `played_frames` to `flush_to_frame` and clears it.
+    flush: AtomicBool,
+    flush_to_frame: AtomicU64,
+    /// Tracks currently in the playback buffer, oldest first.
+    timeline: Mutex<VecDeque<TimelineEntry>>,
+    finished: AtomicBool,
+}
+
+#[cfg(feature = "device")]
+impl PlaybackControl {
+    fn new(output_rate: u32) -> Self {
+        Self {
+            output_rate,
+            request: Mutex::new(None),
+            played_frames: AtomicU64::new(0),
+            flush: AtomicBool::new(false),
+            flush_to_frame: AtomicU64::new(0),
+            timeline: Mutex::new(VecDeque::new()),
+            finished: AtomicBool::new(false),
+        }
+    }
+
+    /// Queues a seek within the audible track; the decode thread picks it up
+    /// on its next iteration.
+    pub fn request_seek(&self, position_secs: f64) -> Result<(), AppError> {
+        self.set_request(PlaybackRequest::Seek(position_secs.max(0.0)))
+    }
+
+    /// Queues an immediate switch to `track`, keeping the output stream and
+    /// analysis running.
+    pub fn request_jump(&self, track: QueuedTrack) -> Result<(), AppError> {
+        self.set_request(PlaybackRequest::Jump(track))
+    }
+
+    fn set_request(&self, request: PlaybackRequest) -> Result<(), AppError> {
+        if self.finished.load(Ordering::SeqCst) {
+            return Err(AppError::Audio("Playback has already finished".into()));
+        }
+        let mut req = self.request.lock()
+            .map_err(|_| AppError::Audio("Failed to lock playback request".into()))?;
+        *req = Some(request);
+        Ok(())
+    }
+
+    fn take_request(&self) -> Option<PlaybackRequest> {
+        self.request.lock().ok().and_then(|mut r| r.take())
+    }
+
+    fn has_request(&self) -> bool {
+        self.request.lock().map(|r| r.is_some()).unwrap_or(false)
+    }
+
+    /// The track reaching the output device and the position within it.
+    fn audible(&self) -> Option<(TimelineEntry, f64)> {
+        let played = self.played_frames.load(Ordering::Relaxed);
+        let timeline = self.timeline.lock().ok()?;
+        let entry = timeline
+            .iter()
+            .rev()
+            .find(|e| e.start_frame <= played)
+            .or_else(|| timeline.front())?
+            .clone();
+        let position = played.saturating_sub(entry.start_frame) as f64 / self.output_rate as f64;
+        Some((entry, position))
+    }
+
+    /// Audible track path, position and duration in seconds.
+    pub fn position(&self) -> Option<(String, f64, f64)> {
+        self.audible()
+            .map(|(entry, position)| (entry.track.path, position, entry.duration_secs))
+    }
+
+    pub fn audible_track(&self) -> Option<QueuedTrack> {
+        self.audible().map(|(entry, _)| entry.track)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::SeqCst)
+    }
+
+    /// Asks the output callback to drop what's buffered and restart the
+    /// timeline at `frame`, and waits for it so audio pushed afterwards isn't
+    /// discarded too. Bounded in case the device has stopped calling back.
+    fn flush_to(&self, frame: u64, running: &AtomicBool) {
+        self.flush_to_frame.store(frame, Ordering::Relaxed);
+        self.flush.store(true, Ordering::Release);
+        for _ in 0..100 {
+            if !self.flush.load(Ordering::Acquire) || !running.load(Ordering::Relaxed) {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+    }
+
+    /// Called from the output callback. Returns true if buffered audio must be
+    /// discarded before playing anything else.
+    pub(super) fn take_flush(&self) -> bool {
+        if !self.flush.load(Ordering::Acquire) {
+            return false;
+        }
+        self.played_frames
+            .store(self.flush_to_frame.load(Ordering::Relaxed), Ordering::Relaxed);
+        self.flush.store(false, Ordering::Release);
+        true
+    }
+
+    pub(super) fn advance(&self, frames: u64) {
+        self.played_frames.fetch_add(frames, Ordering::Relaxed);
+    }
+
+    fn push_track(&self, start_frame: u64, track: QueuedTrack, duration_secs: f64) {
+        if let Ok(mut timeline) = self.timeline.lock() {
+            timeline.push_back(TimelineEntry {
+                start_frame,
+                track,
+                duration_secs,
+            });
+        }
+    }
+
+    /// Replaces the timeline with a single track starting at `start_frame`.
+    fn reset_timeline(&self, start_frame: u64, track: QueuedTrack, duration_secs: f64) {
+        if let Ok(mut timeline) = self.timeline.lock() {
+            timeline.clear();
+        }
+        self.push_track(start_frame, track, duration_secs);
+    }
+
+    /// Drops entries that have been fully played.
+    fn prune_timeline(&self) {
+        let played = self.played_frames.load(Ordering::Relaxed);
+        if let Ok(mut timeline) = self.timeline.lock() {
+            while timeline.len() > 1 && timeline[1].start_frame <= played {
+                timeline.pop_front();
+            }
+        }
+    }
+}
 
-            let mut pending_mono: Vec<f32> = Vec::with_capacity(samples_per_frame * 2);
+pub struct FilePlayer;
+
+#[cfg(feature = "device")]
+impl FilePlayer {
+    /// Plays `first` through an output device, then whatever `queue` hands out.
+    ///
+    /// The decode thread fills a stereo playback buffer; the output stream
+    /// drains it and forwards what it actually played to `producer`, keeping
+    /// the analysis feed locked to what is heard. The next track's decoder is
+    /// opened while the current one plays and its audio follows on in the same
+    /// buffer, so track changes are gapless and the analysis stream never
+    /// restarts.
+    pub fn play_queue(
+        first: QueuedTrack,
+        mut queue: impl TrackQueue,
+        output_device: Option<&str>,
+        producer: impl Producer<Item = f32> + Send + 'static,
+        running: Arc<AtomicBool>,
+        paused: Arc<AtomicBool>,
+    ) -> Result<(cpal::Stream, JoinHandle<()>, FileInfo, Arc<PlaybackControl>), AppError> {
+        let source = AudioSource::open(&first.path)?;
+
+        let device = output::find_output_device(output_device)?;
+        let stream_config = output::output_config(&device, source.sample_rate())?;
+        let output_rate = stream_config.sample_rate.0;
+
+        // Playback buffer: ~250ms of interleaved stereo
+        let playback = AudioRingBuffer::new((output_rate as usize / 4).max(1024) * 2);
+        let (mut playback_producer, playback_consumer) = playback.split();
+
+        let file_info = FileInfo {
+            sample_rate: output_rate,
+            duration_secs: source.duration_secs(),
+        };
+
+        let control = Arc::new(PlaybackControl::new(output_rate));
+        control.reset_timeline(0, first.clone(), source.duration_secs());
+
+        // Start output first so a device failure doesn't leave a decode thread behind
+        let stream = output::start_output(
+            &device,
+            &stream_config,
+            playback_consumer,
+            producer,
+            running.clone(),
+            paused,
+            control.clone(),
+        )?;
+
+        let thread_control = control.clone();
+
+        let handle = std::thread::spawn(move || {
+            let control = thread_control;
+            let mut source = source;
+            let mut track = first;
+            let mut preopened: Option<(QueuedTrack, AudioSource)> = None;
+            let mut preopen_tried = false;
+            let mut resampler = LinearResampler::new(source.sample_rate(), output_rate);
+            let mut stereo: Vec<f32> = Vec::new();
+            let mut pending: Vec<f32> = Vec::new();
+            // Timeline frame of the next sample pushed into the playback buffer
+            let mut written: u64 = 0;
+            let mut audible_id: Option<u64> = None;
+            let mut eof = false;
 
             loop {
                 if !running.load(Ordering::Relaxed) {
-                    break;
+                    return;
                 }
 
-                // Handle pause
-                while paused.load(Ordering::Relaxed) && running.load(Ordering::Relaxed) {
-                    std::thread::sleep(std::time::Duration::from_millis(50));
-                }
-                if !running.load(Ordering::Relaxed) {
-                    break;
+                control.prune_timeline();
+                if let Some((entry, _)) = control.audible() {
+                    if audible_id != Some(entry.track.id) {
+                        audible_id = Some(entry.track.id);
+                        queue.started(&entry.track, entry.duration_secs);
+                    }
                 }
 
-                let packet = match format.next_packet() {
-                    Ok(p) => p,
-                    Err(symphonia::core::errors::Error::IoError(ref e))
-                        if e.kind() == std::io::ErrorKind::UnexpectedEof =>
-                    {
-                        // EOF — playback finished
-                        break;
+                match control.take_request() {
+                    Some(PlaybackRequest::Seek(target)) => {
+                        let Some((entry, _)) = control.audible() else {
+                            continue;
+                        };
+
+                        // The next track may already be decoding; seeks apply
+                        // to what's heard, so go back to it
+                        if entry.track.id != track.id {
+                            match AudioSource::open(&entry.track.path) {
+                                Ok(s) => {
+                                    source = s;
+                                    track = entry.track.clone();
+                                    preopened = None;
+                                    preopen_tried = false;
+                                }
+                                Err(e) => {
+                                    eprintln!("Seek failed: {e}");
+                                    continue;
+                                }
+                            }
+                        }
+
+                        let target = if entry.duration_secs > 0.0 {
+                            target.min(entry.duration_secs)
+                        } else {
+                            target
+                        };
+
+                        written = entry.start_frame + (target * output_rate as f64) as u64;
+                        control.flush_to(written, &running);
+                        control.reset_timeline(entry.start_frame, track.clone(), entry.duration_secs);
+
+                        if let Err(e) = source.seek(target) {
+                            eprintln!("Seek failed: {e}");
+                        }
+                        resampler = LinearResampler::new(source.sample_rate(), output_rate);
+                        eof = false;
+                        continue;
                     }
-                    Err(_) => break,
-                };
+                    Some(PlaybackRequest::Jump(next)) => {
+                        match AudioSource::open(&next.path) {
+                            Ok(s) => {
+                                written = control.played_frames.load(Ordering::Relaxed);
+                                control.flush_to(written, &running);
+                                control.reset_timeline(written, next.clone(), s.duration_secs());
+                                resampler = LinearResampler::new(s.sample_rate(), output_rate);
+                                source = s;
+                                track = next;
+                                preopened = None;
+                                preopen_tried = false;
+                                eof = false;
+                            }
+                            Err(e) => eprintln!("Failed to switch track: {e}"),
+                        }
+                        continue;
+                    }
+                    None => {}
+                }
 
-                if packet.track_id() != track_id {
+                if eof {
+                    // Wait for the device to play out the tail, then report
+                    if playback_producer.is_empty() {
+                        control.finished.store(true, Ordering::SeqCst);
+                        let position = control.audible().map(|(_, p)| p).unwrap_or(0.0);
+                        queue.finished(&track, position);
+                        return;
+                    }
+                    std::thread::sleep(std::time::Duration::from_millis(10));
                     continue;
                 }
 
-                let decoded = match decoder.decode(&packet) {
-                    Ok(d) => d,
-                    Err(_) => continue,
-                };
-
-                let spec = *decoded.spec();
-                let num_frames = decoded.frames();
-                let ch = spec.channels.count().max(1);
-
-                let mut sample_buf = SampleBuffer::<f32>::new(num_frames as u64, spec);
-                sample_buf.copy_interleaved_ref(decoded);
-                let samples = sample_buf.samples();
-
-                // Downmix to mono
-                for frame_idx in 0..num_frames {
-                    let mut sum = 0.0f32;
-                    for c in 0..ch {
-                        let idx = frame_idx * ch + c;
-                        if idx < samples.len() {
-                            sum += samples[idx];
+                stereo.clear();
+                if !source.read_stereo(&mut stereo) {
+                    // End of this track: chain the next one straight on
+                    let Some(next) = queue.next_after(&track) else {
+                        eof = true;
+                        continue;
+                    };
+
+                    let next_source = match preopened.take() {
+                        Some((t, s)) if t == next => Ok(s),
+                        _ => AudioSource::open(&next.path),
+                    };
+
+                    match next_source {
+                        Ok(s) => {
+                            control.push_track(written, next.clone(), s.duration_secs());
+                            resampler = LinearResampler::new(s.sample_rate(), output_rate);
+                            source = s;
+                            track = next;
+                            preopen_tried = false;
+                        }
+                        Err(e) => {
+                            eprintln!("Failed to open next track: {e}");
+                            eof = true;
                         }
                     }
-                    pending_mono.push(sum / ch as f32);
+                    continue;
                 }
 
-                // Pace output: push samples_per_frame at a time
-                while pending_mono.len() >= samples_per_frame {
-                    let frame_start = std::time::Instant::now();
+                pending.clear();
+                resampler.process(&stereo, &mut pending);
+                written += (pending.len() / 2) as u64;
 
-                    // Check running before pushing
+                // Block while the playback buffer is full; the output callback
+                // drains it in real time (and not at all while paused).
+                let mut offset = 0;
+                while offset < pending.len() {
                     if !running.load(Ordering::Relaxed) {
                         return;
                     }
-
-                    // Wait if buffer is > 75% full
-                    let vacant = producer.vacant_len();
-                    if vacant < samples_per_frame {
+                    if control.has_request() {
+                        // The rest of this packet is stale once a request lands
+                        break;
+                    }
+                    offset += playback_producer.push_slice(&pending[offset..]);
+                    if offset < pending.len() {
                         std::thread::sleep(std::time::Duration::from_millis(5));
-                        continue;
                     }
+                }
 
-                    let chunk: Vec<f32> = pending_mono.drain(..samples_per_frame).collect();
-                    producer.push_slice(&chunk);
-
-                    let elapsed = frame_start.elapsed();
-                    if elapsed < pace_interval {
-                        std::thread::sleep(pace_interval - elapsed);
+                // Open the next track's decoder ahead of time so the
+                // transition doesn't wait on file I/O and probing
+                if !preopen_tried {
+                    preopen_tried = true;
+                    if let Some(next) = queue.next_after(&track) {
+                        match AudioSource::open(&next.path) {
+                            Ok(s) => preopened = Some((next, s)),
+                            Err(e) => eprintln!("Failed to pre-open next track: {e}"),
+                        }
                     }
                 }
             }
+        });
+
+        Ok((stream, handle, file_info, control))
+    }
+}
+
+/// Converts interleaved samples with `channels` channels to interleaved stereo.
+/// Mono is duplicated to both sides; extra channels beyond L/R are dropped.
+fn to_stereo(samples: &[f32], channels: usize, out: &mut Vec<f32>) {
+    for frame in samples.chunks_exact(channels) {
+        if channels == 1 {
+            out.push(frame[0]);
+            out.push(frame[0]);
+        } else {
+            out.push(frame[0]);
+            out.push(frame[1]);
+        }
+    }
+}
+
+#[cfg(feature = "device")]
+/// Linear-interpolating stereo resampler, used when the output device can't
+/// run at the file's native rate.
+struct LinearResampler {
+    /// Input frames advanced per output frame.
+    step: f64,
+    /// Read position in input frames; -1.0 refers to `last`.
+    pos: f64,
+    last: [f32; 2],
+}
+
+#[cfg(feature = "device")]
+impl LinearResampler {
+    fn new(from_rate: u32, to_rate: u32) -> Self {
+        Self {
+            step: from_rate as f64 / to_rate.max(1) as f64,
+            pos: 0.0,
+            last: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        if self.step == 1.0 {
+            out.extend_from_slice(input);
+            return;
+        }
 
-            // Push remaining samples
-            if !pending_mono.is_empty() && running.load(Ordering::Relaxed) {
-                producer.push_slice(&pending_mono);
+        let frames = input.len() / 2;
+        if frames == 0 {
+            return;
+        }
+
+        let frame = |i: isize| -> [f32; 2] {
+            if i < 0 {
+                self.last
+            } else {
+                [input[i as usize * 2], input[i as usize * 2 + 1]]
             }
-        });
+        };
+
+        while self.pos < (frames - 1) as f64 {
+            let i = self.pos.floor() as isize;
+            let frac = (self.pos - i as f64) as f32;
+            let a = frame(i);
+            let b = frame(i + 1);
+            out.push(a[0] + (b[0] - a[0]) * frac);
+            out.push(a[1] + (b[1] - a[1]) * frac);
+            self.pos += self.step;
+        }
+
+        self.pos -= frames as f64;
+        self.last = [input[(frames - 1) * 2], input[(frames - 1) * 2 + 1]];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        Ok((handle, file_info))
+    #[cfg(feature = "device")]
+    fn track(id: u64) -> QueuedTrack {
+        QueuedTrack {
+            id,
+            path: format!("track{id}.mp3"),
+        }
+    }
+
+    #[cfg(feature = "device")]
+    #[test]
+    fn test_seek_flush_moves_position() {
+        let control = PlaybackControl::new(48000);
+        control.reset_timeline(0, track(1), 10.0);
+        control.advance(48000);
+        let (_, position) = control.audible().unwrap();
+        assert!((position - 1.0).abs() < 1e-9);
+
+        let running = AtomicBool::new(false);
+        control.flush_to(5 * 48000, &running);
+        assert!(control.take_flush());
+        assert!(!control.take_flush());
+        let (_, position) = control.audible().unwrap();
+        assert!((position - 5.0).abs() < 1e-9);
+    }
+
+    #[cfg(feature = "device")]
+    #[test]
+    fn test_timeline_follows_track_boundary() {
+        let control = PlaybackControl::new(1000);
+        control.reset_timeline(0, track(1), 2.0);
+        control.push_track(2000, track(2), 3.0);
+
+        control.advance(1500);
+        let (entry, position) = control.audible().unwrap();
+        assert_eq!(entry.track.id, 1);
+        assert!((position - 1.5).abs() < 1e-9);
+
+        control.advance(1000);
+        control.prune_timeline();
+        let (entry, position) = control.audible().unwrap();
+        assert_eq!(entry.track.id, 2);
+        assert!((position - 0.5).abs() < 1e-9);
+        assert_eq!(control.timeline.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_to_stereo_mono_duplicates() {
+        let mut out = Vec::new();
+        to_stereo(&[0.1, 0.2], 1, &mut out);
+        assert_eq!(out, vec![0.1, 0.1, 0.2, 0.2]);
+    }
+
+    #[cfg(feature = "device")]
+    #[test]
+    fn test_resampler_output_length() {
+        // 1 second at 44.1kHz resampled to 48kHz, fed in uneven chunks
+        let input = vec![0.5f32; 44100 * 2];
+        let mut resampler = LinearResampler::new(44100, 48000);
+        let mut out = Vec::new();
+        for chunk in input.chunks(1152 * 2) {
+            resampler.process(chunk, &mut out);
+        }
+        let frames = out.len() / 2;
+        assert!((frames as i64 - 48000).abs() <= 2, "Got {} frames", frames);
+        assert!(out.iter().all(|&s| (s - 0.5).abs() < 1e-6));
     }
 }
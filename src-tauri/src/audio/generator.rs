@@ -0,0 +1,505 @@
+use std::f64::consts::TAU;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ringbuf::traits::Producer;
+use serde::{Deserialize, Serialize};
+
+/// Rate the generator runs at.
+pub const GENERATOR_SAMPLE_RATE: u32 = 48_000;
+/// Audio generated per block by the source thread, in seconds.
+const BLOCK_SECS: f64 = 0.01;
+/// How fast each click decays, per second.
+const CLICK_DECAY: f64 = 30.0;
+const CLICK_HZ: f64 = 60.0;
+
+/// Test signal played by the generator source.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Signal {
+    #[serde(rename_all = "camelCase")]
+    Sine {
+        frequency_hz: f32,
+    },
+    /// Exponential sweep from `start_hz` to `end_hz`, starting over every
+    /// `duration_secs`.
+    #[serde(rename_all = "camelCase")]
+    Sweep {
+        start_hz: f32,
+        end_hz: f32,
+        duration_secs: f32,
+    },
+    WhiteNoise,
+    /// Noise falling 3 dB per octave, equal energy in every octave.
+    PinkNoise,
+    /// A decaying low thump on every beat.
+    Clicks {
+        bpm: f32,
+    },
+    /// Equal-level sines at each frequency.
+    #[serde(rename_all = "camelCase")]
+    Chord {
+        frequencies_hz: Vec<f32>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneratorConfig {
+    pub signal: Signal,
+    /// Peak level, 0..1.
+    #[serde(default = "default_amplitude")]
+    pub amplitude: f32,
+}
+
+fn default_amplitude() -> f32 {
+    0.5
+}
+
+impl GeneratorConfig {
+    /// Clamps levels, frequencies and tempos to what the generator can play.
+    pub fn validated(mut self) -> Self {
+        let nyquist = GENERATOR_SAMPLE_RATE as f32 / 2.0;
+        let hz = |f: &mut f32| *f = f.clamp(1.0, nyquist);
+        self.amplitude = self.amplitude.clamp(0.0, 1.0);
+        match &mut self.signal {
+            Signal::Sine { frequency_hz } => hz(frequency_hz),
+            Signal::Sweep {
+                start_hz,
+                end_hz,
+                duration_secs,
+            } => {
+                hz(start_hz);
+                hz(end_hz);
+                *duration_secs = duration_secs.clamp(0.1, 600.0);
+            }
+            Signal::Clicks { bpm } => *bpm = bpm.clamp(30.0, 300.0),
+            Signal::Chord { frequencies_hz } => frequencies_hz.iter_mut().for_each(hz),
+            Signal::WhiteNoise | Signal::PinkNoise => {}
+        }
+        self
+    }
+}
+
+/// Produces a [`Signal`] sample by sample, the same on both channels.
+pub struct Generator {
+    signal: Signal,
+    amplitude: f64,
+    sample_rate: u32,
+    /// Samples produced so far.
+    position: u64,
+    /// Phase of each tone, in cycles.
+    phases: Vec<f64>,
+    /// Pink noise filter state.
+    pink: [f64; 3],
+    rng: fastrand::Rng,
+}
+
+impl Generator {
+    pub fn new(config: &GeneratorConfig, sample_rate: u32) -> Self {
+        let tones = match &config.signal {
+            Signal::Chord { frequencies_hz } => frequencies_hz.len(),
+            _ => 1,
+        };
+        Self {
+            signal: config.signal.clone(),
+            amplitude: config.amplitude as f64,
+            sample_rate,
+            position: 0,
+            phases: vec![0.0; tones],
+            pink: [0.0; 3],
+            rng: fastrand::Rng::with_seed(0),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Fills `out` with interleaved left/right pairs.
+    pub fn fill_interleaved(&mut self, out: &mut [f32]) {
+        for pair in out.chunks_exact_mut(2) {
+            let sample = self.next_sample();
+            pair[0] = sample;
+            pair[1] = sample;
+        }
+    }
+
+    fn next_sample(&mut self) -> f32 {
+        let rate = self.sample_rate as f64;
+        let t = self.position as f64 / rate;
+        self.position += 1;
+
+        let value = match &self.signal {
+            Signal::Sine { frequency_hz } => {
+                advance(&mut self.phases[0], *frequency_hz as f64 / rate)
+            }
+            Signal::Sweep {
+                start_hz,
+                end_hz,
+                duration_secs,
+            } => {
+                let progress = (t % *duration_secs as f64) / *duration_secs as f64;
+                let hz = *start_hz as f64 * (*end_hz as f64 / *start_hz as f64).powf(progress);
+                advance(&mut self.phases[0], hz / rate)
+            }
+            Signal::WhiteNoise => self.rng.f64() * 2.0 - 1.0,
+            Signal::PinkNoise => {
+                // Paul Kellet's economy filter: three one-pole sections
+                // approximating -3 dB/octave over the audio band
+                let white = self.rng.f64() * 2.0 - 1.0;
+                let [b0, b1, b2] = &mut self.pink;
+                *b0 = 0.99765 * *b0 + white * 0.0990460;
+                *b1 = 0.96300 * *b1 + white * 0.2965164;
+                *b2 = 0.57000 * *b2 + white * 1.0526913;
+                ((*b0 + *b1 + *b2 + white * 0.1848) * 0.25).clamp(-1.0, 1.0)
+            }
+            Signal::Clicks { bpm } => {
+                let since = t % (60.0 / *bpm as f64);
+                (TAU * CLICK_HZ * since).sin() * (-since * CLICK_DECAY).exp()
+            }
+            Signal::Chord { frequencies_hz } => {
+                let sum: f64 = self
+                    .phases
+                    .iter_mut()
+                    .zip(frequencies_hz)
+                    .map(|(phase, &hz)| advance(phase, hz as f64 / rate))
+                    .sum();
+                sum / frequencies_hz.len().max(1) as f64
+            }
+        };
+        (value * self.amplitude) as f32
+    }
+}
+
+/// Sine at `phase` cycles, then steps the phase by `step` cycles.
+fn advance(phase: &mut f64, step: f64) -> f64 {
+    let value = (TAU * *phase).sin();
+    *phase = (*phase + step).fract();
+    value
+}
+
+/// Turns elapsed time into whole blocks of frames to generate, carrying the
+/// remainder so the long-run rate is exact.
+struct Pacer {
+    rate: f64,
+    block_frames: usize,
+    clock: Instant,
+    /// Frames of real time not yet generated.
+    owed: f64,
+}
+
+impl Pacer {
+    fn new(rate: f64, block_frames: usize, now: Instant) -> Self {
+        Self {
+            rate,
+            block_frames,
+            clock: now,
+            owed: 0.0,
+        }
+    }
+
+    /// Blocks due at `now`. Time spent paused is skipped, not made up.
+    fn due(&mut self, now: Instant, paused: bool) -> usize {
+        if paused {
+            self.owed = 0.0;
+        } else {
+            self.owed += now.duration_since(self.clock).as_secs_f64() * self.rate;
+        }
+        self.clock = now;
+
+        let blocks = (self.owed / self.block_frames as f64) as usize;
+        self.owed -= (blocks * self.block_frames) as f64;
+        blocks
+    }
+}
+
+/// Runs `generator` in real time on its own thread, pushing interleaved
+/// stereo into `producer` the way a capture callback would. Nothing is fed
+/// while `paused`, and the signal picks up where it left off.
+pub fn start_generator(
+    mut generator: Generator,
+    mut producer: impl Producer<Item = f32> + Send + 'static,
+    running: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let rate = generator.sample_rate() as f64;
+        let block_frames = (rate * BLOCK_SECS) as usize;
+        let mut block = vec![0.0f32; 2 * block_frames];
+        let mut pacer = Pacer::new(rate, block_frames, Instant::now());
+
+        while running.load(Ordering::Relaxed) {
+            let due = pacer.due(Instant::now(), paused.load(Ordering::Relaxed));
+            for _ in 0..due {
+                generator.fill_interleaved(&mut block);
+                // Like a capture callback, drop what doesn't fit
+                let fits = producer.vacant_len() / 2 * 2;
+                producer.push_slice(&block[..fits.min(block.len())]);
+            }
+            std::thread::sleep(Duration::from_secs_f64(BLOCK_SECS / 2.0));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::analysis::{AnalysisResult, AudioAnalyzer};
+    use crate::audio::beat::BeatDetector;
+    use crate::audio::ring_buffer::{AudioRingBuffer, StereoWindow};
+    use crate::audio::types::AudioConfig;
+
+    /// Feeds `secs` of `signal` through the ring and window into the
+    /// analyzer and beat detector the way the live analysis thread does,
+    /// one hop at a time on the generator's clock.
+    fn run(
+        signal: Signal,
+        config: &AudioConfig,
+        secs: f64,
+        mut on_frame: impl FnMut(&AnalysisResult, bool, f32, f64),
+    ) -> BeatDetector {
+        let rate = GENERATOR_SAMPLE_RATE;
+        let mut generator = Generator::new(
+            &GeneratorConfig {
+                signal,
+                amplitude: 0.8,
+            }
+            .validated(),
+            rate,
+        );
+        let hop = config.hop_for_rate(rate);
+        let (mut producer, mut consumer) = AudioRingBuffer::new(4 * config.fft_size).split();
+        let mut window = StereoWindow::new(config.fft_size);
+        let mut analyzer = AudioAnalyzer::new(config.fft_size, rate);
+        analyzer.configure(config, rate as f32 / hop as f32);
+        let mut beat_detector = BeatDetector::new(config.sensitivity);
+        let mut block = vec![0.0f32; 2 * hop];
+
+        let mut produced = 0;
+        while (produced as f64) < secs * rate as f64 {
+            generator.fill_interleaved(&mut block);
+            producer.push_slice(&block);
+            produced += hop;
+            window.fill_from(&mut consumer, |_, _, _| {});
+            if window.is_full() {
+                window.take_fresh();
+                let timestamp = produced as f64 / rate as f64;
+                if let Some(result) = analyzer.analyze(window.mid()) {
+                    let (beat, bpm) = beat_detector.detect(&result.spectrum, timestamp);
+                    on_frame(result, beat, bpm, timestamp);
+                }
+            }
+        }
+        beat_detector
+    }
+
+    #[test]
+    fn test_output_stays_in_range() {
+        for signal in [
+            Signal::WhiteNoise,
+            Signal::PinkNoise,
+            Signal::Chord {
+                frequencies_hz: vec![220.0, 277.2, 329.6],
+            },
+        ] {
+            let mut generator = Generator::new(
+                &GeneratorConfig {
+                    signal,
+                    amplitude: 1.0,
+                },
+                GENERATOR_SAMPLE_RATE,
+            );
+            let mut out = vec![0.0f32; 96_000];
+            generator.fill_interleaved(&mut out);
+            assert!(out.iter().all(|s| s.abs() <= 1.0));
+            assert!(out.chunks_exact(2).all(|p| p[0] == p[1]));
+            assert!(out.iter().any(|&s| s.abs() > 0.1));
+        }
+    }
+
+    #[test]
+    fn test_sine_peaks_at_its_frequency() {
+        let config = AudioConfig::default();
+        let mut last = (0, 0.0, 1.0);
+        run(
+            Signal::Sine {
+                frequency_hz: 1000.0,
+            },
+            &config,
+            1.0,
+            |result, _, _, _| {
+                let peak = result
+                    .spectrum
+                    .iter()
+                    .enumerate()
+                    .max_by(|a, b| a.1.total_cmp(b.1))
+                    .map_or(0, |(i, _)| i);
+                last = (peak, result.centroid, result.flatness);
+            },
+        );
+        let (peak, centroid, flatness) = last;
+        let bin_hz = GENERATOR_SAMPLE_RATE as f32 / config.fft_size as f32;
+        assert!(
+            (peak as f32 * bin_hz - 1000.0).abs() <= bin_hz,
+            "peak bin {peak}"
+        );
+        assert!((centroid - 1000.0).abs() < 100.0, "centroid {centroid}");
+        assert!(flatness < 0.05, "flatness {flatness}");
+    }
+
+    #[test]
+    fn test_sweep_rises() {
+        let config = AudioConfig::default();
+        let mut centroids = Vec::new();
+        let sweep = Signal::Sweep {
+            start_hz: 100.0,
+            end_hz: 10_000.0,
+            duration_secs: 4.0,
+        };
+        run(sweep, &config, 3.9, |result, _, _, t| {
+            if t > 0.5 {
+                centroids.push(result.centroid);
+            }
+        });
+        assert!(centroids.windows(30).all(|w| w[29] > w[0]), "{centroids:?}");
+    }
+
+    #[test]
+    fn test_noise_colours() {
+        let config = AudioConfig::default();
+        let mean_slope = |signal| {
+            let (mut slope, mut flatness, mut n) = (0.0, 0.0, 0.0);
+            run(signal, &config, 2.0, |result, _, _, _| {
+                slope += result.slope;
+                flatness += result.flatness;
+                n += 1.0;
+            });
+            (slope / n, flatness / n)
+        };
+        let (white_slope, white_flatness) = mean_slope(Signal::WhiteNoise);
+        let (pink_slope, pink_flatness) = mean_slope(Signal::PinkNoise);
+        assert!(white_flatness > 0.3, "white flatness {white_flatness}");
+        assert!(pink_flatness < white_flatness);
+        assert!(
+            pink_slope < white_slope,
+            "pink {pink_slope}, white {white_slope}"
+        );
+    }
+
+    #[test]
+    fn test_chord_chroma() {
+        let config = AudioConfig {
+            chroma: true,
+            fft_size: 8192,
+            ..Default::default()
+        };
+        // C major: C4, E4, G4
+        let chord = Signal::Chord {
+            frequencies_hz: vec![261.63, 329.63, 392.0],
+        };
+        let mut chroma = Vec::new();
+        run(chord, &config, 1.0, |result, _, _, _| {
+            chroma = result.chroma.clone()
+        });
+        let mut classes: Vec<usize> = (0..12).collect();
+        classes.sort_by(|&a, &b| chroma[b].total_cmp(&chroma[a]));
+        classes.truncate(3);
+        classes.sort();
+        assert_eq!(classes, [0, 4, 7], "{chroma:?}");
+    }
+
+    #[test]
+    fn test_click_track_beats_and_tempo() {
+        let config = AudioConfig::default();
+        let mut beats = Vec::new();
+        let mut bpm = 0.0;
+        let detector = run(
+            Signal::Clicks { bpm: 120.0 },
+            &config,
+            12.0,
+            |_, beat, frame_bpm, t| {
+                if beat {
+                    beats.push(t);
+                }
+                bpm = frame_bpm;
+            },
+        );
+        assert!(beats.len() >= 20, "{} beats", beats.len());
+        // Each beat within a frame of a click
+        for t in beats {
+            let off = t % 0.5;
+            assert!(off.min(0.5 - off) < 1.5 / 60.0, "beat at {t}");
+        }
+        assert!((bpm - 120.0).abs() < 1.0, "{bpm}");
+        assert!(detector.tempo_confidence() > 0.5);
+        assert!(detector.position().is_some());
+    }
+
+    #[test]
+    fn test_pacer_keeps_real_time() {
+        let rate = GENERATOR_SAMPLE_RATE as f64;
+        let block_frames = (rate * BLOCK_SECS) as usize;
+        let start = Instant::now();
+        let mut pacer = Pacer::new(rate, block_frames, start);
+
+        // Uneven ticks adding up to 1.005 s: the remainders carry over, so
+        // exactly 100 blocks of 10 ms are due
+        let mut at = start;
+        let mut blocks = 0;
+        for ms in [3, 7, 13, 1, 26].into_iter().cycle().take(100).chain([5]) {
+            at += Duration::from_millis(ms);
+            blocks += pacer.due(at, false);
+        }
+        assert_eq!(at - start, Duration::from_millis(1005));
+        assert_eq!(blocks, 100);
+    }
+
+    #[test]
+    fn test_pacer_skips_paused_time() {
+        let rate = GENERATOR_SAMPLE_RATE as f64;
+        let block_frames = (rate * BLOCK_SECS) as usize;
+        let start = Instant::now();
+        let mut pacer = Pacer::new(rate, block_frames, start);
+
+        assert_eq!(pacer.due(start + Duration::from_millis(25), false), 2);
+        assert_eq!(pacer.due(start + Duration::from_secs(5), true), 0);
+        // Resuming doesn't make up the paused time, or the half block before it
+        assert_eq!(pacer.due(start + Duration::from_millis(5_015), false), 1);
+    }
+
+    #[test]
+    fn test_source_thread_feeds_in_real_time() {
+        let (producer, mut consumer) = AudioRingBuffer::new(GENERATOR_SAMPLE_RATE as usize).split();
+        let running = Arc::new(AtomicBool::new(true));
+        let paused = Arc::new(AtomicBool::new(false));
+        let generator = Generator::new(
+            &GeneratorConfig {
+                signal: Signal::Sine {
+                    frequency_hz: 440.0,
+                },
+                amplitude: 0.5,
+            },
+            GENERATOR_SAMPLE_RATE,
+        );
+        let start = Instant::now();
+        let handle = start_generator(generator, producer, running.clone(), paused.clone());
+        let deadline = start + Duration::from_secs(5);
+        while ringbuf::traits::Observer::is_empty(&consumer) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        running.store(false, Ordering::SeqCst);
+        handle.join().unwrap();
+        let elapsed = start.elapsed().as_secs_f64();
+
+        // Something arrived, and never more than the time that passed
+        let mut samples = Vec::new();
+        while let Some(s) = ringbuf::traits::Consumer::try_pop(&mut consumer) {
+            samples.push(s);
+        }
+        let secs = samples.len() as f64 / 2.0 / GENERATOR_SAMPLE_RATE as f64;
+        assert!(secs > 0.0 && secs <= elapsed, "{secs} s in {elapsed} s");
+        assert_eq!(samples.len() % 2, 0);
+    }
+}
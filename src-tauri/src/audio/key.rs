@@ -0,0 +1,257 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+pub const PITCH_CLASSES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// Krumhansl-Kessler key profiles, tonic first.
+const MAJOR_PROFILE: [f32; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f32; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+/// Time constant of the chroma history the live key is estimated from.
+const KEY_WINDOW_SECS: f64 = 30.0;
+/// Audible time needed before a key is reported.
+const KEY_MIN_SECS: f64 = 2.0;
+/// Cosine similarity a frame needs with a triad to be called that chord.
+const CHORD_MIN_SIMILARITY: f32 = 0.7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    Major,
+    Minor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyEstimate {
+    /// Pitch class of the tonic, 0 = C.
+    pub tonic: usize,
+    pub mode: Mode,
+    /// Correlation with the key profile, 0..1.
+    pub confidence: f32,
+}
+
+impl fmt::Display for KeyEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mode = match self.mode {
+            Mode::Major => "major",
+            Mode::Minor => "minor",
+        };
+        write!(f, "{} {mode}", PITCH_CLASSES[self.tonic])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Chord {
+    /// Pitch class of the root, 0 = C.
+    pub root: usize,
+    pub quality: Mode,
+    /// Cosine similarity with the triad, 0..1.
+    pub confidence: f32,
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let suffix = match self.quality {
+            Mode::Major => "",
+            Mode::Minor => "m",
+        };
+        write!(f, "{}{suffix}", PITCH_CLASSES[self.root])
+    }
+}
+
+/// Best-matching key for a chroma vector (C first), by correlation with the
+/// 24 rotated major and minor profiles. `None` for silence or a flat vector.
+pub fn estimate_key(chroma: &[f32]) -> Option<KeyEstimate> {
+    if chroma.len() != 12 {
+        return None;
+    }
+    let mut best: Option<KeyEstimate> = None;
+    for (mode, profile) in [(Mode::Major, &MAJOR_PROFILE), (Mode::Minor, &MINOR_PROFILE)] {
+        for tonic in 0..12 {
+            let r = correlation(chroma, profile, tonic)?;
+            if best.is_none_or(|b| r > b.confidence) {
+                best = Some(KeyEstimate {
+                    tonic,
+                    mode,
+                    confidence: r,
+                });
+            }
+        }
+    }
+    best.map(|b| KeyEstimate {
+        confidence: b.confidence.max(0.0),
+        ..b
+    })
+}
+
+/// Pearson correlation of `chroma` with `profile` moved up to `tonic`.
+fn correlation(chroma: &[f32], profile: &[f32; 12], tonic: usize) -> Option<f32> {
+    let mean_c = chroma.iter().sum::<f32>() / 12.0;
+    let mean_p = profile.iter().sum::<f32>() / 12.0;
+    let (mut cov, mut var_c, mut var_p) = (0.0, 0.0, 0.0);
+    for (i, &c) in chroma.iter().enumerate() {
+        let p = profile[(i + 12 - tonic) % 12];
+        cov += (c - mean_c) * (p - mean_p);
+        var_c += (c - mean_c) * (c - mean_c);
+        var_p += (p - mean_p) * (p - mean_p);
+    }
+    (var_c > 1e-12).then(|| cov / (var_c * var_p).sqrt())
+}
+
+/// Closest major or minor triad to one frame's chroma, if any is close
+/// enough to call.
+pub fn estimate_chord(chroma: &[f32]) -> Option<Chord> {
+    if chroma.len() != 12 {
+        return None;
+    }
+    let norm = chroma.iter().map(|c| c * c).sum::<f32>().sqrt();
+    if norm < 1e-6 {
+        return None;
+    }
+    let mut best: Option<Chord> = None;
+    for (quality, third) in [(Mode::Major, 4), (Mode::Minor, 3)] {
+        for root in 0..12 {
+            let dot = chroma[root] + chroma[(root + third) % 12] + chroma[(root + 7) % 12];
+            let similarity = dot / (norm * 3f32.sqrt());
+            if best.is_none_or(|b| similarity > b.confidence) {
+                best = Some(Chord {
+                    root,
+                    quality,
+                    confidence: similarity,
+                });
+            }
+        }
+    }
+    best.filter(|b| b.confidence >= CHORD_MIN_SIMILARITY)
+}
+
+/// Tracks the key of a live stream from per-frame chroma, weighting recent
+/// audio over a window of about [`KEY_WINDOW_SECS`] so it follows
+/// modulations. Decay is by timestamp, so the frame rate doesn't matter.
+#[derive(Default)]
+pub struct KeyDetector {
+    history: [f32; 12],
+    heard_secs: f64,
+    last_timestamp: Option<f64>,
+    estimate: Option<KeyEstimate>,
+}
+
+impl KeyDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Folds in one frame's chroma and returns the current estimate.
+    /// Silent frames keep the previous estimate.
+    pub fn update(&mut self, chroma: &[f32], timestamp: f64) -> Option<KeyEstimate> {
+        let dt = self
+            .last_timestamp
+            .map_or(0.0, |last| (timestamp - last).max(0.0));
+        self.last_timestamp = Some(timestamp);
+
+        if chroma.len() != 12 || chroma.iter().all(|&c| c <= 0.0) {
+            return self.estimate;
+        }
+
+        let decay = (-dt / KEY_WINDOW_SECS).exp() as f32;
+        for (h, &c) in self.history.iter_mut().zip(chroma) {
+            *h = *h * decay + c;
+        }
+        self.heard_secs += dt;
+
+        if self.heard_secs >= KEY_MIN_SECS {
+            self.estimate = estimate_key(&self.history);
+        }
+        self.estimate
+    }
+
+    pub fn estimate(&self) -> Option<KeyEstimate> {
+        self.estimate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Chroma with the given pitch classes at 1.0 and the rest at `rest`.
+    fn chroma_of(classes: &[usize], rest: f32) -> [f32; 12] {
+        let mut chroma = [rest; 12];
+        for &c in classes {
+            chroma[c] = 1.0;
+        }
+        chroma
+    }
+
+    #[test]
+    fn test_scales_give_their_keys() {
+        // C major scale with a strong tonic triad
+        let mut c_major = chroma_of(&[0, 2, 4, 5, 7, 9, 11], 0.0);
+        for i in [0, 4, 7] {
+            c_major[i] = 2.0;
+        }
+        let key = estimate_key(&c_major).unwrap();
+        assert_eq!((key.tonic, key.mode), (0, Mode::Major));
+        assert_eq!(key.to_string(), "C major");
+
+        // Same notes centred on A
+        let mut a_minor = chroma_of(&[0, 2, 4, 5, 7, 9, 11], 0.0);
+        for i in [9, 0, 4] {
+            a_minor[i] = 2.0;
+        }
+        let key = estimate_key(&a_minor).unwrap();
+        assert_eq!((key.tonic, key.mode), (9, Mode::Minor));
+
+        assert!(estimate_key(&[0.0; 12]).is_none());
+    }
+
+    #[test]
+    fn test_triads_give_their_chords() {
+        let chord = estimate_chord(&chroma_of(&[7, 11, 2], 0.1)).unwrap();
+        assert_eq!((chord.root, chord.quality), (7, Mode::Major));
+        assert_eq!(chord.to_string(), "G");
+
+        let chord = estimate_chord(&chroma_of(&[9, 0, 4], 0.1)).unwrap();
+        assert_eq!(chord.to_string(), "Am");
+
+        // Noise matches no triad well
+        assert!(estimate_chord(&[1.0; 12]).is_none());
+        assert!(estimate_chord(&[0.0; 12]).is_none());
+    }
+
+    #[test]
+    fn test_detector_follows_a_modulation() {
+        let c_major = chroma_of(&[0, 4, 7], 0.2);
+        let e_major = chroma_of(&[4, 8, 11], 0.2);
+        let mut detector = KeyDetector::new();
+
+        // Nothing until enough has been heard
+        assert!(detector.update(&c_major, 0.0).is_none());
+        let mut t = 0.0;
+        for _ in 0..60 * 30 {
+            t += 1.0 / 60.0;
+            detector.update(&c_major, t);
+        }
+        assert_eq!(detector.estimate().unwrap().to_string(), "C major");
+
+        // Silence holds the estimate
+        detector.update(&[0.0; 12], t + 1.0);
+        assert_eq!(detector.estimate().unwrap().to_string(), "C major");
+
+        // A minute in E major moves it over
+        for _ in 0..30 * 60 {
+            t += 1.0 / 30.0;
+            detector.update(&e_major, t);
+        }
+        assert_eq!(detector.estimate().unwrap().to_string(), "E major");
+    }
+}
@@ -1,6 +1,14 @@
 pub mod analysis;
 pub mod beat;
+#[cfg(feature = "device")]
 pub mod capture;
 pub mod file_player;
+pub mod generator;
+pub mod key;
+pub mod offline;
+#[cfg(feature = "device")]
+pub mod output;
+pub mod playlist;
 pub mod ring_buffer;
+pub mod tempo;
 pub mod types;
@@ -0,0 +1,721 @@
+use serde::{Deserialize, Serialize};
+
+use super::analysis::{AudioAnalyzer, Loudness, LoudnessMeter, SpectrumBand, StereoAnalyzer};
+use super::beat::BeatDetector;
+use super::file_player::AudioSource;
+use super::key::{estimate_key, KeyDetector, KeyEstimate};
+use super::types::{AudioConfig, AudioFrame};
+use crate::error::AppError;
+
+/// Number of points in the overview envelope.
+const ENVELOPE_POINTS: usize = 1000;
+/// Feature blocks used for section detection, in seconds.
+const SECTION_BLOCK_SECS: f64 = 1.0;
+/// Blocks compared on either side of a candidate section boundary.
+const SECTION_CONTEXT_BLOCKS: usize = 8;
+const MIN_SECTION_SECS: f64 = 8.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvelopePoint {
+    pub time: f64,
+    pub min: f32,
+    pub max: f32,
+    pub rms: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TempoPoint {
+    pub time: f64,
+    pub bpm: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Section {
+    pub start: f64,
+    pub end: f64,
+    pub avg_rms: f32,
+    pub avg_centroid: f32,
+    /// "low", "medium" or "high", relative to the rest of the track.
+    pub energy: String,
+}
+
+/// Whole-track averages in the same shape `classify_audio` takes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureSummary {
+    pub avg_rms: f32,
+    pub avg_centroid: f32,
+    pub avg_flux: f32,
+    pub avg_zcr: f32,
+    pub bpm: f32,
+    pub beat_regularity: f32,
+    /// Mean MFCCs; empty when they weren't computed.
+    #[serde(default)]
+    pub mfcc: Vec<f32>,
+    /// Mean chroma vector, C first; empty when it wasn't computed.
+    #[serde(default)]
+    pub chroma: Vec<f32>,
+    #[serde(default)]
+    pub key: Option<KeyEstimate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OfflineAnalysis {
+    pub duration_secs: f64,
+    pub sample_rate: u32,
+    /// Width of one FFT bin in Hz.
+    pub bin_hz: f32,
+    /// Frequency ranges of the frame spectra; empty for the linear layout.
+    pub bands: Vec<SpectrumBand>,
+    pub frame_rate: f64,
+    pub frame_count: usize,
+    /// Every analysis frame; only filled in when requested since it is large.
+    pub frames: Option<Vec<AudioFrame>>,
+    pub envelope: Vec<EnvelopePoint>,
+    pub beats: Vec<f64>,
+    pub tempo_map: Vec<TempoPoint>,
+    pub sections: Vec<Section>,
+    pub summary: FeatureSummary,
+    /// Loudness of the whole file.
+    pub loudness: Loudness,
+}
+
+/// Decodes `path` and runs the live analysis pipeline over it as fast as the
+/// CPU allows. Frames are spaced 1/`target_fps` apart just like live playback,
+/// so results line up with what the visuals would have seen.
+pub fn analyze_file(
+    path: &str,
+    config: &AudioConfig,
+    include_frames: bool,
+) -> Result<OfflineAnalysis, AppError> {
+    let mut frames = Vec::new();
+    let mut analysis = analyze_file_with(path, config, |frame| {
+        if include_frames {
+            frames.push(frame);
+        }
+    })?;
+    analysis.frames = include_frames.then_some(frames);
+    Ok(analysis)
+}
+
+/// Like [`analyze_file`], but hands each frame to `on_frame` as it is
+/// produced instead of collecting them.
+pub fn analyze_file_with(
+    path: &str,
+    config: &AudioConfig,
+    mut on_frame: impl FnMut(AudioFrame),
+) -> Result<OfflineAnalysis, AppError> {
+    let mut source = AudioSource::open(path)?;
+    let sample_rate = source.sample_rate();
+    let fft_size = config.fft_size;
+    let hop = config.hop_for_rate(sample_rate);
+
+    let mut analyzer = AudioAnalyzer::new(fft_size, sample_rate);
+    analyzer.configure(config, sample_rate as f32 / hop as f32);
+    let mut beat_detector = BeatDetector::new(config.sensitivity);
+    beat_detector.set_onset_bands(&config.onset_bands, analyzer.bin_hz());
+    let mut key_detector = KeyDetector::new();
+    let mut meter = LoudnessMeter::new(sample_rate);
+
+    let mut stereo_analyzer = (!config.mono_downmix).then(|| {
+        let mut stereo = StereoAnalyzer::new(fft_size, sample_rate);
+        stereo.configure(config, sample_rate as f32 / hop as f32);
+        stereo
+    });
+
+    let mut stereo: Vec<f32> = Vec::new();
+    // Interleaved left/right, like the live ring
+    let mut window: Vec<f32> = Vec::with_capacity(2 * (fft_size + hop));
+    let mut left = vec![0.0f32; fft_size];
+    let mut right = vec![0.0f32; fft_size];
+    let mut mid = vec![0.0f32; fft_size];
+    // Frames consumed off the front of `window` so far
+    let mut consumed: u64 = 0;
+    // Frames the loudness meter has seen; it runs up to each frame's
+    // timestamp and no further
+    let mut metered: u64 = 0;
+    // Frames still to drop when a hop is longer than what's buffered
+    let mut skip: usize = 0;
+
+    let mut acc = TimelineAccumulator::default();
+
+    loop {
+        stereo.clear();
+        let more = source.read_stereo(&mut stereo);
+
+        let dropped = skip.min(stereo.len() / 2);
+        skip -= dropped;
+        meter_interleaved(&mut meter, &stereo[..dropped * 2]);
+        consumed += dropped as u64;
+        metered += dropped as u64;
+        window.extend_from_slice(&stereo[dropped * 2..]);
+
+        while window.len() >= 2 * fft_size {
+            let timestamp = (consumed + fft_size as u64) as f64 / sample_rate as f64;
+            let from = (metered - consumed) as usize;
+            meter_interleaved(&mut meter, &window[from * 2..fft_size * 2]);
+            metered = consumed + fft_size as u64;
+
+            for (i, pair) in window[..2 * fft_size].chunks_exact(2).enumerate() {
+                left[i] = pair[0];
+                right[i] = pair[1];
+                mid[i] = (pair[0] + pair[1]) * 0.5;
+            }
+            let (min, max) = mid[fft_size - hop.min(fft_size)..]
+                .iter()
+                .fold((0.0f32, 0.0f32), |(lo, hi), &s| (lo.min(s), hi.max(s)));
+
+            if let Some(result) = analyzer.analyze(&mid) {
+                let (beat, bpm) = beat_detector.detect(&result.spectrum, timestamp);
+                let mut frame = AudioFrame::from_analysis(result, beat, bpm, timestamp);
+                frame.tempo_confidence = beat_detector.tempo_confidence();
+                frame.onsets = beat_detector.onsets();
+                frame.beat_position = beat_detector.position();
+                frame.key = key_detector.update(&result.chroma, timestamp);
+                frame.loudness = meter.loudness();
+                if let Some(stereo) = stereo_analyzer.as_mut() {
+                    frame.stereo = stereo.analyze(&left, &right).cloned();
+                }
+                acc.push(&frame, min, max);
+                on_frame(frame);
+            }
+
+            // Whatever leaves the window unanalyzed still counts
+            let buffered = window.len() / 2;
+            let leaving = hop.min(buffered);
+            let from = (metered - consumed) as usize;
+            if from < leaving {
+                meter_interleaved(&mut meter, &window[from * 2..leaving * 2]);
+                metered = consumed + leaving as u64;
+            }
+            if hop <= buffered {
+                window.drain(..2 * hop);
+                consumed += hop as u64;
+            } else {
+                skip = hop - buffered;
+                consumed += buffered as u64;
+                window.clear();
+            }
+        }
+
+        if !more {
+            break;
+        }
+    }
+    // The tail too short for another frame
+    let from = (metered - consumed) as usize;
+    meter_interleaved(&mut meter, &window[from * 2..]);
+
+    let duration_secs = if source.duration_secs() > 0.0 {
+        source.duration_secs()
+    } else {
+        (consumed + window.len() as u64 / 2) as f64 / sample_rate as f64
+    };
+
+    Ok(OfflineAnalysis {
+        duration_secs,
+        sample_rate,
+        bin_hz: analyzer.bin_hz(),
+        bands: analyzer.bands().to_vec(),
+        frame_rate: sample_rate as f64 / hop as f64,
+        frame_count: acc.count(),
+        frames: None,
+        envelope: acc.envelope(ENVELOPE_POINTS),
+        sections: acc.sections(),
+        summary: acc.summary(),
+        loudness: meter.loudness(),
+        beats: acc.beats,
+        tempo_map: acc.tempo_map,
+    })
+}
+
+/// Meters interleaved left/right audio. Always as a pair: loudness sums the
+/// channels, so it doesn't depend on whether stereo analysis is on.
+fn meter_interleaved(meter: &mut LoudnessMeter, interleaved: &[f32]) {
+    let mut left = [0.0f32; 256];
+    let mut right = [0.0f32; 256];
+    for chunk in interleaved.chunks(2 * left.len()) {
+        let frames = chunk.len() / 2;
+        for (i, pair) in chunk.chunks_exact(2).enumerate() {
+            left[i] = pair[0];
+            right[i] = pair[1];
+        }
+        meter.process_stereo(&left[..frames], &right[..frames]);
+    }
+}
+
+/// Collects the per-frame values the summaries are built from, without
+/// keeping whole frames around.
+#[derive(Default)]
+struct TimelineAccumulator {
+    times: Vec<f64>,
+    rms: Vec<f32>,
+    centroid: Vec<f32>,
+    flux: Vec<f32>,
+    zcr: Vec<f32>,
+    /// Running sums of the optional per-frame vectors.
+    mfcc_sum: Vec<f32>,
+    chroma_sum: Vec<f32>,
+    peaks: Vec<(f32, f32)>,
+    beats: Vec<f64>,
+    tempo_map: Vec<TempoPoint>,
+    last_bpm: f32,
+}
+
+impl TimelineAccumulator {
+    fn push(&mut self, frame: &AudioFrame, min: f32, max: f32) {
+        self.times.push(frame.timestamp);
+        self.rms.push(frame.rms);
+        self.centroid.push(frame.centroid);
+        self.flux.push(frame.flux);
+        self.zcr.push(frame.zcr);
+        add_to(&mut self.mfcc_sum, &frame.mfcc);
+        add_to(&mut self.chroma_sum, &frame.chroma);
+        self.peaks.push((min, max));
+
+        if frame.beat {
+            self.beats.push(frame.timestamp);
+        }
+
+        // Record tempo changes of more than 1 BPM
+        if frame.bpm > 0.0 && (frame.bpm - self.last_bpm).abs() > 1.0 {
+            self.last_bpm = frame.bpm;
+            self.tempo_map.push(TempoPoint {
+                time: frame.timestamp,
+                bpm: frame.bpm,
+            });
+        }
+    }
+
+    fn count(&self) -> usize {
+        self.times.len()
+    }
+
+    /// Min/max/RMS overview with at most `points` entries.
+    fn envelope(&self, points: usize) -> Vec<EnvelopePoint> {
+        let n = self.count();
+        if n == 0 {
+            return Vec::new();
+        }
+        let per_point = n.div_ceil(points);
+        (0..n)
+            .step_by(per_point)
+            .map(|start| {
+                let end = (start + per_point).min(n);
+                let (min, max) = self.peaks[start..end]
+                    .iter()
+                    .fold((0.0f32, 0.0f32), |(lo, hi), &(a, b)| (lo.min(a), hi.max(b)));
+                let rms = (self.rms[start..end].iter().map(|r| r * r).sum::<f32>()
+                    / (end - start) as f32)
+                    .sqrt();
+                EnvelopePoint {
+                    time: self.times[start],
+                    min,
+                    max,
+                    rms,
+                }
+            })
+            .collect()
+    }
+
+    fn summary(&self) -> FeatureSummary {
+        let n = self.count();
+        if n == 0 {
+            return FeatureSummary::default();
+        }
+        let mean = |v: &[f32]| v.iter().sum::<f32>() / v.len() as f32;
+        let mean_of_sums = |sums: &[f32]| sums.iter().map(|s| s / n as f32).collect::<Vec<_>>();
+        // Whole-track key, rather than the live detector's recent window
+        let chroma = mean_of_sums(&self.chroma_sum);
+        FeatureSummary {
+            avg_rms: mean(&self.rms),
+            avg_centroid: mean(&self.centroid),
+            avg_flux: mean(&self.flux),
+            avg_zcr: mean(&self.zcr),
+            bpm: self.tempo_map.last().map(|t| t.bpm).unwrap_or(0.0),
+            beat_regularity: beat_regularity(&self.beats),
+            mfcc: mean_of_sums(&self.mfcc_sum),
+            key: estimate_key(&chroma),
+            chroma,
+        }
+    }
+
+    /// Splits the track where the average loudness/brightness of the blocks
+    /// before a point differs most from the blocks after it.
+    fn sections(&self) -> Vec<Section> {
+        let Some(&end_time) = self.times.last() else {
+            return Vec::new();
+        };
+
+        // Per-block (rms, centroid) means
+        let block_count = (end_time / SECTION_BLOCK_SECS).ceil().max(1.0) as usize;
+        let mut sums = vec![(0.0f32, 0.0f32, 0usize); block_count];
+        for (i, &t) in self.times.iter().enumerate() {
+            let b = ((t / SECTION_BLOCK_SECS) as usize).min(block_count - 1);
+            sums[b].0 += self.rms[i];
+            sums[b].1 += self.centroid[i];
+            sums[b].2 += 1;
+        }
+        let blocks: Vec<(f32, f32)> = sums
+            .iter()
+            .map(|&(r, c, k)| {
+                if k > 0 {
+                    (r / k as f32, c / k as f32)
+                } else {
+                    (0.0, 0.0)
+                }
+            })
+            .collect();
+
+        let max_rms = blocks.iter().map(|b| b.0).fold(0.0f32, f32::max).max(1e-6);
+        let max_centroid = blocks.iter().map(|b| b.1).fold(0.0f32, f32::max).max(1e-6);
+        let norm: Vec<(f32, f32)> = blocks
+            .iter()
+            .map(|&(r, c)| (r / max_rms, c / max_centroid))
+            .collect();
+
+        // Novelty: distance between the mean of the context before and after
+        let ctx = SECTION_CONTEXT_BLOCKS;
+        let mut novelty = vec![0.0f32; norm.len()];
+        for i in ctx..norm.len().saturating_sub(ctx) {
+            let mean = |s: &[(f32, f32)]| {
+                let n = s.len() as f32;
+                (
+                    s.iter().map(|v| v.0).sum::<f32>() / n,
+                    s.iter().map(|v| v.1).sum::<f32>() / n,
+                )
+            };
+            let before = mean(&norm[i - ctx..i]);
+            let after = mean(&norm[i..i + ctx]);
+            novelty[i] = ((before.0 - after.0).powi(2) + (before.1 - after.1).powi(2)).sqrt();
+        }
+
+        let mean_novelty = novelty.iter().sum::<f32>() / novelty.len() as f32;
+        let threshold = (mean_novelty * 2.0).max(0.1);
+        let min_blocks = (MIN_SECTION_SECS / SECTION_BLOCK_SECS) as usize;
+
+        let mut boundaries = vec![0usize];
+        for i in 1..novelty.len().saturating_sub(1) {
+            let is_peak = novelty[i] >= novelty[i - 1] && novelty[i] > novelty[i + 1];
+            let far_enough = i - boundaries.last().copied().unwrap_or(0) >= min_blocks;
+            if is_peak && novelty[i] > threshold && far_enough {
+                boundaries.push(i);
+            }
+        }
+        boundaries.push(blocks.len());
+
+        let track_rms = blocks.iter().map(|b| b.0).sum::<f32>() / blocks.len() as f32;
+        boundaries
+            .windows(2)
+            .filter(|w| w[1] > w[0])
+            .map(|w| {
+                let slice = &blocks[w[0]..w[1]];
+                let n = slice.len() as f32;
+                let avg_rms = slice.iter().map(|b| b.0).sum::<f32>() / n;
+                let avg_centroid = slice.iter().map(|b| b.1).sum::<f32>() / n;
+                let energy = if avg_rms < track_rms * 0.75 {
+                    "low"
+                } else if avg_rms > track_rms * 1.25 {
+                    "high"
+                } else {
+                    "medium"
+                };
+                Section {
+                    start: w[0] as f64 * SECTION_BLOCK_SECS,
+                    end: (w[1] as f64 * SECTION_BLOCK_SECS).min(end_time),
+                    avg_rms,
+                    avg_centroid,
+                    energy: energy.to_string(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Adds `values` into `sums`, sizing `sums` on first use.
+fn add_to(sums: &mut Vec<f32>, values: &[f32]) {
+    if sums.len() < values.len() {
+        sums.resize(values.len(), 0.0);
+    }
+    for (s, v) in sums.iter_mut().zip(values) {
+        *s += v;
+    }
+}
+
+/// 1 minus the coefficient of variation of beat intervals, clamped to
+/// [0, 1]; 0.5 when there are too few beats to tell. Matches the frontend's
+/// live `computeBeatRegularity`.
+fn beat_regularity(beats: &[f64]) -> f32 {
+    if beats.len() < 3 {
+        return 0.5;
+    }
+    let intervals: Vec<f64> = beats.windows(2).map(|w| w[1] - w[0]).collect();
+    let mean = intervals.iter().sum::<f64>() / intervals.len() as f64;
+    if mean <= 0.0 {
+        return 0.5;
+    }
+    let variance = intervals
+        .iter()
+        .map(|i| (i - mean) * (i - mean))
+        .sum::<f64>()
+        / intervals.len() as f64;
+    let cv = variance.sqrt() / mean;
+    (1.0 - cv.min(1.0)) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(timestamp: f64, rms: f32, beat: bool, bpm: f32) -> AudioFrame {
+        AudioFrame {
+            rms,
+            centroid: 2000.0,
+            beat,
+            bpm,
+            timestamp,
+            ..Default::default()
+        }
+    }
+
+    /// Writes a 16-bit mono WAV of `secs` seconds with a decaying 60Hz thump
+    /// every `interval` seconds.
+    fn write_click_wav(path: &std::path::Path, secs: f64, interval: f64) {
+        let sample_rate = 44100u32;
+        let n = (secs * sample_rate as f64) as usize;
+        let samples: Vec<f64> = (0..n)
+            .map(|i| {
+                let t = i as f64 / sample_rate as f64;
+                let since = t % interval;
+                let env = (-since * 30.0).exp();
+                (2.0 * std::f64::consts::PI * 60.0 * t).sin() * env * 0.8
+            })
+            .collect();
+        write_wav(path, sample_rate, 1, &samples);
+    }
+
+    /// Writes interleaved `samples` (-1..1) as a 16-bit WAV.
+    fn write_wav(path: &std::path::Path, sample_rate: u32, channels: u16, samples: &[f64]) {
+        let samples: Vec<i16> = samples
+            .iter()
+            .map(|v| (v * i16::MAX as f64) as i16)
+            .collect();
+
+        let data_len = (samples.len() * 2) as u32;
+        let mut bytes = Vec::with_capacity(44 + data_len as usize);
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        bytes.extend_from_slice(&channels.to_le_bytes());
+        bytes.extend_from_slice(&sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&(sample_rate * 2 * channels as u32).to_le_bytes());
+        bytes.extend_from_slice(&(2 * channels).to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_len.to_le_bytes());
+        for s in samples {
+            bytes.extend_from_slice(&s.to_le_bytes());
+        }
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn test_analyze_click_track_file() {
+        let path = std::env::temp_dir().join("synthwave_offline_click.wav");
+        write_click_wav(&path, 12.0, 0.5);
+
+        let config = AudioConfig::default();
+        let result = analyze_file(path.to_str().unwrap(), &config, true).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert!((result.duration_secs - 12.0).abs() < 0.01);
+        assert_eq!(
+            result.frames.as_ref().map(|f| f.len()),
+            Some(result.frame_count)
+        );
+        // 60 fps over 12s, less the first window
+        assert!(
+            result.frame_count > 650,
+            "Only {} frames",
+            result.frame_count
+        );
+        assert!(
+            result.beats.len() >= 15,
+            "Only {} beats",
+            result.beats.len()
+        );
+        assert!(
+            (result.summary.bpm - 120.0).abs() < 10.0,
+            "BPM {} not close to 120",
+            result.summary.bpm
+        );
+        assert!(result.summary.beat_regularity > 0.8);
+        // Thumps peaking at 0.8, mostly quiet in between
+        assert!(result.loudness.integrated > -40.0 && result.loudness.integrated < -5.0);
+        assert!(result.loudness.true_peak < 0.0 && result.loudness.true_peak > -3.0);
+        // A mono file plays the same on both sides
+        let frames = result.frames.unwrap();
+        let stereo = frames[100].stereo.as_ref().unwrap();
+        assert!((stereo.correlation - 1.0).abs() < 1e-3);
+        assert!(stereo.balance.abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_loudness_follows_frame_timestamps() {
+        // A 1 kHz tone that jumps by 34 dB at 3 s
+        let path = std::env::temp_dir().join("synthwave_offline_step.wav");
+        let sample_rate = 44100;
+        let step = 3.0;
+        let samples: Vec<f64> = (0..5 * sample_rate as usize)
+            .map(|i| {
+                let t = i as f64 / sample_rate as f64;
+                let level = if t < step { 0.01 } else { 0.5 };
+                level * (2.0 * std::f64::consts::PI * 1000.0 * t).sin()
+            })
+            .collect();
+        write_wav(&path, sample_rate, 1, &samples);
+
+        let config = AudioConfig::default();
+        let result = analyze_file(path.to_str().unwrap(), &config, true).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let frames = result.frames.unwrap();
+        let momentary_at = |t: f64| {
+            frames
+                .iter()
+                .rev()
+                .find(|f| f.timestamp <= t)
+                .unwrap()
+                .loudness
+                .momentary
+        };
+
+        // Up to the step, frames haven't heard the loud part yet
+        let quiet = momentary_at(2.5);
+        let at_step = momentary_at(step);
+        assert!((at_step - quiet).abs() < 0.5, "{at_step} vs {quiet}");
+        assert!(momentary_at(step + 0.5) > quiet + 30.0);
+
+        // Each frame reads exactly what a meter fed up to its timestamp
+        // reads, and the file total covers every sample once
+        let decoded: Vec<f32> = samples
+            .iter()
+            .map(|v| (v * i16::MAX as f64) as i16 as f32 / 32768.0)
+            .collect();
+        let mut meter = LoudnessMeter::new(sample_rate);
+        let mut fed = 0;
+        for frame in &frames {
+            let end = (frame.timestamp * sample_rate as f64).round() as usize;
+            meter.process_stereo(&decoded[fed..end], &decoded[fed..end]);
+            fed = end;
+            assert_eq!(frame.loudness, meter.loudness(), "at {}", frame.timestamp);
+        }
+        meter.process_stereo(&decoded[fed..], &decoded[fed..]);
+        assert_eq!(result.loudness, meter.loudness());
+    }
+
+    #[test]
+    fn test_loudness_ignores_mono_downmix() {
+        // Different tones and levels on each side
+        let path = std::env::temp_dir().join("synthwave_offline_stereo_lufs.wav");
+        let sample_rate = 44100;
+        let samples: Vec<f64> = (0..3 * sample_rate as usize)
+            .flat_map(|i| {
+                let t = i as f64 / sample_rate as f64;
+                let tau = 2.0 * std::f64::consts::PI;
+                [0.5 * (tau * 440.0 * t).sin(), 0.2 * (tau * 660.0 * t).sin()]
+            })
+            .collect();
+        write_wav(&path, sample_rate, 2, &samples);
+
+        let analyze = |mono_downmix| {
+            let config = AudioConfig {
+                mono_downmix,
+                ..Default::default()
+            };
+            analyze_file(path.to_str().unwrap(), &config, true).unwrap()
+        };
+        let stereo = analyze(false);
+        let mono = analyze(true);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(stereo.loudness, mono.loudness);
+        assert!(stereo.loudness.integrated.is_finite());
+        let frames = stereo.frames.unwrap().into_iter().zip(mono.frames.unwrap());
+        for (a, b) in frames {
+            assert_eq!(a.loudness, b.loudness, "at {}", a.timestamp);
+        }
+    }
+
+    #[test]
+    fn test_mono_downmix_skips_stereo() {
+        let path = std::env::temp_dir().join("synthwave_offline_mono.wav");
+        write_click_wav(&path, 2.0, 0.5);
+
+        let config = AudioConfig {
+            mono_downmix: true,
+            ..Default::default()
+        };
+        let result = analyze_file(path.to_str().unwrap(), &config, true).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let frames = result.frames.unwrap();
+        assert!(!frames.is_empty());
+        assert!(frames.iter().all(|f| f.stereo.is_none()));
+    }
+
+    #[test]
+    fn test_beat_regularity_steady() {
+        let beats: Vec<f64> = (0..10).map(|i| i as f64 * 0.5).collect();
+        assert!((beat_regularity(&beats) - 1.0).abs() < 1e-6);
+        assert!((beat_regularity(&beats[..2]) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sections_split_on_loudness_change() {
+        // 30s quiet then 30s loud at 10 frames per second
+        let mut acc = TimelineAccumulator::default();
+        for i in 0..600 {
+            let t = i as f64 / 10.0;
+            let rms = if t < 30.0 { 0.05 } else { 0.5 };
+            acc.push(&frame(t, rms, false, 0.0), -rms, rms);
+        }
+
+        let sections = acc.sections();
+        assert_eq!(sections.len(), 2, "Sections: {:?}", sections);
+        assert!((sections[1].start - 30.0).abs() <= 1.0);
+        assert_eq!(sections[0].energy, "low");
+        assert_eq!(sections[1].energy, "high");
+    }
+
+    #[test]
+    fn test_envelope_point_count() {
+        let mut acc = TimelineAccumulator::default();
+        for i in 0..5000 {
+            acc.push(&frame(i as f64 / 60.0, 0.1, i % 30 == 0, 120.0), -0.2, 0.3);
+        }
+        let envelope = acc.envelope(ENVELOPE_POINTS);
+        assert!(envelope.len() <= ENVELOPE_POINTS);
+        assert!(envelope.iter().all(|p| p.min == -0.2 && p.max == 0.3));
+        assert_eq!(acc.tempo_map.len(), 1);
+        assert_eq!(acc.beats.len(), 167);
+    }
+
+    #[test]
+    fn test_summary_averages_optional_vectors() {
+        let mut acc = TimelineAccumulator::default();
+        for (i, c) in [0.0, 1.0].into_iter().enumerate() {
+            let mut f = frame(i as f64, 0.1, false, 0.0);
+            f.chroma = vec![c; 12];
+            acc.push(&f, 0.0, 0.0);
+        }
+        let summary = acc.summary();
+        assert!(summary.mfcc.is_empty());
+        assert_eq!(summary.chroma, vec![0.5; 12]);
+    }
+}
@@ -0,0 +1,174 @@
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{Device, SampleFormat, SampleRate, Stream, StreamConfig};
+use ringbuf::traits::{Consumer, Observer, Producer};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::file_player::PlaybackControl;
+use super::types::AudioDevice;
+use crate::error::AppError;
+
+pub fn list_output_devices() -> Result<Vec<AudioDevice>, AppError> {
+    let host = cpal::default_host();
+    let default_output = host.default_output_device();
+    let default_name = default_output
+        .as_ref()
+        .and_then(|d| d.name().ok())
+        .unwrap_or_default();
+
+    let mut devices = Vec::new();
+
+    if let Ok(output_devices) = host.output_devices() {
+        for device in output_devices {
+            if let Ok(name) = device.name() {
+                devices.push(AudioDevice {
+                    is_default: name == default_name,
+                    name,
+                    is_input: false,
+                });
+            }
+        }
+    }
+
+    Ok(devices)
+}
+
+pub fn find_output_device(name: Option<&str>) -> Result<Device, AppError> {
+    let host = cpal::default_host();
+
+    if let Some(name) = name {
+        let devices = host
+            .output_devices()
+            .map_err(|e| AppError::Audio(format!("Failed to enumerate devices: {e}")))?;
+
+        for device in devices {
+            if let Ok(n) = device.name() {
+                if n == name {
+                    return Ok(device);
+                }
+            }
+        }
+        return Err(AppError::Audio(format!("Output device '{name}' not found")));
+    }
+
+    host.default_output_device()
+        .ok_or_else(|| AppError::Audio("No default output device found".into()))
+}
+
+/// Picks an output stream config, preferring `preferred_rate` (the file's native
+/// rate) and falling back to the device default when it isn't supported.
+/// Playback is always f32, so a device that can't take f32 at either rate is
+/// an error.
+pub fn output_config(device: &Device, preferred_rate: u32) -> Result<StreamConfig, AppError> {
+    let default_config = device
+        .default_output_config()
+        .map_err(|e| AppError::Audio(format!("Failed to get output config: {e}")))?;
+
+    let channels = default_config.channels();
+    if channels == 0 {
+        return Err(AppError::Audio("Device reports 0 channels".into()));
+    }
+
+    let default_rate = default_config.sample_rate().0;
+    let sample_rate = if supports_f32(device, channels, preferred_rate) {
+        preferred_rate
+    } else if supports_f32(device, channels, default_rate) {
+        default_rate
+    } else {
+        return Err(AppError::Audio(format!(
+            "Output device doesn't support f32 samples at {preferred_rate} or {default_rate} Hz"
+        )));
+    };
+
+    Ok(StreamConfig {
+        channels,
+        sample_rate: SampleRate(sample_rate),
+        buffer_size: cpal::BufferSize::Default,
+    })
+}
+
+fn supports_f32(device: &Device, channels: u16, rate: u32) -> bool {
+    device
+        .supported_output_configs()
+        .map(|mut configs| {
+            configs.any(|c| {
+                c.channels() == channels
+                    && c.sample_format() == SampleFormat::F32
+                    && c.min_sample_rate().0 <= rate
+                    && rate <= c.max_sample_rate().0
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// Starts an output stream that plays interleaved stereo from `source`.
+///
+/// Every frame that reaches the device is also pushed into `analysis` as
+/// an interleaved left/right pair, so the analysis feed runs on the output device's clock
+/// rather than on a timer. Underruns and paused periods play silence and
+/// feed nothing. Played frames advance `control`'s position.
+pub fn start_output(
+    device: &Device,
+    stream_config: &StreamConfig,
+    mut source: ringbuf::HeapCons<f32>,
+    mut analysis: impl Producer<Item = f32> + Send + 'static,
+    running: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    control: Arc<PlaybackControl>,
+) -> Result<Stream, AppError> {
+    let channels = stream_config.channels as usize;
+
+    let stream = device
+        .build_output_stream(
+            stream_config,
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                // Pending seek: drop pre-seek audio even while paused
+                if control.take_flush() {
+                    source.clear();
+                }
+
+                if !running.load(Ordering::Relaxed) || paused.load(Ordering::Relaxed) {
+                    data.fill(0.0);
+                    return;
+                }
+
+                // Only consume whole stereo frames so L/R never drift apart
+                let available = source.occupied_len() / 2;
+
+                for (i, frame) in data.chunks_mut(channels).enumerate() {
+                    if i >= available {
+                        frame.fill(0.0);
+                        continue;
+                    }
+
+                    let left = source.try_pop().unwrap_or(0.0);
+                    let right = source.try_pop().unwrap_or(0.0);
+
+                    if frame.len() == 1 {
+                        frame[0] = (left + right) * 0.5;
+                    } else {
+                        frame[0] = left;
+                        frame[1] = right;
+                        frame[2..].fill(0.0);
+                    }
+
+                    if analysis.vacant_len() >= 2 {
+                        let _ = analysis.push_slice(&[left, right]);
+                    }
+                }
+
+                control.advance(available.min(data.len() / channels) as u64);
+            },
+            |err| {
+                eprintln!("Audio output stream error: {err}");
+            },
+            None,
+        )
+        .map_err(|e| AppError::Audio(format!("Failed to build output stream: {e}")))?;
+
+    stream
+        .play()
+        .map_err(|e| AppError::Audio(format!("Failed to start output stream: {e}")))?;
+
+    Ok(stream)
+}
@@ -0,0 +1,266 @@
+use serde::{Deserialize, Serialize};
+
+use super::file_player::QueuedTrack;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RepeatMode {
+    #[default]
+    Off,
+    All,
+    One,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistTrack {
+    pub id: u64,
+    pub path: String,
+}
+
+impl PlaylistTrack {
+    pub fn queued(&self) -> QueuedTrack {
+        QueuedTrack {
+            id: self.id,
+            path: self.path.clone(),
+        }
+    }
+}
+
+/// Ordered list of files with shuffle/repeat state. Tracks carry stable ids
+/// so the same file can appear more than once.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Playlist {
+    pub tracks: Vec<PlaylistTrack>,
+    pub current_id: Option<u64>,
+    pub shuffle: bool,
+    pub repeat: RepeatMode,
+    /// Play order as track ids when shuffled.
+    #[serde(default)]
+    shuffle_order: Vec<u64>,
+    #[serde(default)]
+    next_id: u64,
+    /// The current track if it was removed while playing, with its place in
+    /// the play order, so playback can still move on from it.
+    #[serde(skip)]
+    removed_current: Option<(u64, usize)>,
+}
+
+impl Playlist {
+    pub fn add(&mut self, paths: Vec<String>) {
+        for path in paths {
+            let id = self.next_id;
+            self.next_id += 1;
+            self.tracks.push(PlaylistTrack { id, path });
+            if self.shuffle {
+                // Slot new tracks somewhere after the current one
+                let after = self.current_position().map(|p| p + 1).unwrap_or(0);
+                let at = fastrand::usize(after..=self.shuffle_order.len());
+                self.shuffle_order.insert(at, id);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<PlaylistTrack> {
+        if index >= self.tracks.len() {
+            return None;
+        }
+        let id = self.tracks[index].id;
+        let position = self.order().iter().position(|&t| t == id);
+        let removed = self.tracks.remove(index);
+        self.shuffle_order.retain(|&id| id != removed.id);
+        if self.current_id == Some(removed.id) {
+            self.current_id = None;
+            self.removed_current = position.map(|at| (removed.id, at));
+        }
+        Some(removed)
+    }
+
+    /// Moves the track at `from` so it ends up at index `to`.
+    pub fn move_track(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.tracks.len() || to >= self.tracks.len() {
+            return false;
+        }
+        let track = self.tracks.remove(from);
+        self.tracks.insert(to, track);
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.tracks.clear();
+        self.shuffle_order.clear();
+        self.current_id = None;
+        self.removed_current = None;
+    }
+
+    pub fn set_shuffle(&mut self, shuffle: bool) {
+        self.shuffle = shuffle;
+        self.shuffle_order.clear();
+        if !shuffle {
+            return;
+        }
+        // Keep the current track first so the rest follows on from it
+        let mut rest: Vec<u64> = self
+            .tracks
+            .iter()
+            .map(|t| t.id)
+            .filter(|&id| Some(id) != self.current_id)
+            .collect();
+        fastrand::shuffle(&mut rest);
+        self.shuffle_order.extend(self.current_id);
+        self.shuffle_order.extend(rest);
+    }
+
+    pub fn set_repeat(&mut self, repeat: RepeatMode) {
+        self.repeat = repeat;
+    }
+
+    pub fn set_current(&mut self, id: u64) {
+        if self.track(id).is_some() {
+            self.current_id = Some(id);
+        }
+    }
+
+    pub fn track(&self, id: u64) -> Option<&PlaylistTrack> {
+        self.tracks.iter().find(|t| t.id == id)
+    }
+
+    pub fn index_of(&self, id: u64) -> Option<usize> {
+        self.tracks.iter().position(|t| t.id == id)
+    }
+
+    fn order(&self) -> Vec<u64> {
+        if self.shuffle {
+            self.shuffle_order.clone()
+        } else {
+            self.tracks.iter().map(|t| t.id).collect()
+        }
+    }
+
+    fn current_position(&self) -> Option<usize> {
+        let current = self.current_id?;
+        self.order().iter().position(|&id| id == current)
+    }
+
+    /// Track that plays automatically after `id`, honouring shuffle and repeat.
+    pub fn next_after(&self, id: u64) -> Option<&PlaylistTrack> {
+        if self.repeat == RepeatMode::One {
+            if let Some(track) = self.track(id) {
+                return Some(track);
+            }
+        }
+        self.step(id, 1)
+    }
+
+    /// Track a "next" button should go to. Unlike `next_after`, repeat-one
+    /// doesn't hold it on the same track.
+    pub fn skip_forward(&self, id: u64) -> Option<&PlaylistTrack> {
+        self.step(id, 1)
+    }
+
+    pub fn skip_back(&self, id: u64) -> Option<&PlaylistTrack> {
+        self.step(id, -1)
+    }
+
+    fn step(&self, id: u64, delta: isize) -> Option<&PlaylistTrack> {
+        let order = self.order();
+        let len = order.len() as isize;
+        if len == 0 {
+            return None;
+        }
+        let pos = match order.iter().position(|&t| t == id) {
+            Some(pos) => pos as isize,
+            // A removed current track: its successor now sits at its old place
+            None => match self.removed_current {
+                Some((removed, at)) if removed == id => at as isize - (delta > 0) as isize,
+                _ => return None,
+            },
+        };
+        let mut next = pos + delta;
+        if next < 0 || next >= len {
+            if self.repeat == RepeatMode::Off {
+                return None;
+            }
+            next = next.rem_euclid(len);
+        }
+        self.track(order[next as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playlist(n: usize) -> Playlist {
+        let mut p = Playlist::default();
+        p.add((0..n).map(|i| format!("{i}.mp3")).collect());
+        p
+    }
+
+    #[test]
+    fn test_next_respects_repeat() {
+        let mut p = playlist(3);
+        let ids: Vec<u64> = p.tracks.iter().map(|t| t.id).collect();
+
+        assert_eq!(p.next_after(ids[0]).map(|t| t.id), Some(ids[1]));
+        assert!(p.next_after(ids[2]).is_none());
+
+        p.set_repeat(RepeatMode::All);
+        assert_eq!(p.next_after(ids[2]).map(|t| t.id), Some(ids[0]));
+
+        p.set_repeat(RepeatMode::One);
+        assert_eq!(p.next_after(ids[1]).map(|t| t.id), Some(ids[1]));
+        assert_eq!(p.skip_forward(ids[1]).map(|t| t.id), Some(ids[2]));
+    }
+
+    #[test]
+    fn test_shuffle_visits_every_track_once() {
+        let mut p = playlist(8);
+        p.set_current(p.tracks[3].id);
+        p.set_shuffle(true);
+
+        let mut seen = vec![p.current_id.unwrap()];
+        while let Some(next) = p.next_after(*seen.last().unwrap()) {
+            seen.push(next.id);
+        }
+        assert_eq!(seen[0], p.tracks[3].id);
+        seen.sort_unstable();
+        let mut all: Vec<u64> = p.tracks.iter().map(|t| t.id).collect();
+        all.sort_unstable();
+        assert_eq!(seen, all);
+    }
+
+    #[test]
+    fn test_remove_and_move() {
+        let mut p = playlist(3);
+        let first = p.tracks[0].id;
+        p.set_current(first);
+        assert!(p.move_track(0, 2));
+        assert_eq!(p.index_of(first), Some(2));
+
+        p.remove(2);
+        assert!(p.current_id.is_none());
+        assert_eq!(p.tracks.len(), 2);
+    }
+
+    #[test]
+    fn test_removing_current_track_moves_on() {
+        let mut p = playlist(4);
+        let ids: Vec<u64> = p.tracks.iter().map(|t| t.id).collect();
+        p.set_current(ids[1]);
+
+        // Removed while audible: playback continues with what followed it
+        p.remove(1);
+        assert_eq!(p.next_after(ids[1]).map(|t| t.id), Some(ids[2]));
+        assert_eq!(p.skip_forward(ids[1]).map(|t| t.id), Some(ids[2]));
+        assert_eq!(p.skip_back(ids[1]).map(|t| t.id), Some(ids[0]));
+
+        // The last track: nothing follows unless repeating
+        p.set_current(ids[3]);
+        p.remove(2);
+        assert!(p.next_after(ids[3]).is_none());
+        p.set_repeat(RepeatMode::All);
+        assert_eq!(p.next_after(ids[3]).map(|t| t.id), Some(ids[0]));
+    }
+}
@@ -1,8 +1,19 @@
 use ringbuf::{
-    traits::Split,
+    traits::{Consumer, Split},
     HeapRb,
 };
 
+/// Highest device rate the analysis ring is sized for.
+const MAX_SAMPLE_RATE: usize = 192_000;
+
+/// Capacity for the ring between an audio callback and the analysis thread,
+/// in interleaved stereo samples: room for a full window plus two analysis
+/// ticks of audio, so a slow tick doesn't make the producer drop the newest
+/// samples.
+pub fn analysis_capacity(fft_size: usize, target_fps: u32) -> usize {
+    2 * (fft_size * 4).max(fft_size + 2 * MAX_SAMPLE_RATE / target_fps.max(1) as usize)
+}
+
 pub struct AudioRingBuffer {
     producer: ringbuf::HeapProd<f32>,
     consumer: ringbuf::HeapCons<f32>,
@@ -20,10 +31,223 @@ impl AudioRingBuffer {
     }
 }
 
+/// The most recent `len` samples of a stream, oldest first.
+///
+/// Lets the analysis thread run overlapping windows: each tick drains
+/// whatever arrived, and the window always ends at the newest sample.
+pub struct SlidingWindow {
+    samples: Vec<f32>,
+    chunk: Vec<f32>,
+    filled: usize,
+    fresh: usize,
+}
+
+impl SlidingWindow {
+    pub fn new(len: usize) -> Self {
+        Self {
+            samples: vec![0.0; len],
+            chunk: vec![0.0; len],
+            filled: 0,
+            fresh: 0,
+        }
+    }
+
+    /// Appends samples, dropping the oldest ones to keep the length fixed.
+    pub fn push_slice(&mut self, new: &[f32]) {
+        Self::shift_in(&mut self.samples, new);
+        self.advance(new.len());
+    }
+
+    /// Moves everything `consumer` holds into the window, handing each
+    /// drained chunk to `on_chunk` in order so callers that need every sample
+    /// (not just the last window) see them all. Returns how many samples were
+    /// read.
+    pub fn fill_from(
+        &mut self,
+        consumer: &mut impl Consumer<Item = f32>,
+        mut on_chunk: impl FnMut(&[f32]),
+    ) -> usize {
+        let mut total = 0;
+        loop {
+            let read = consumer.pop_slice(&mut self.chunk);
+            if read > 0 {
+                on_chunk(&self.chunk[..read]);
+            }
+            Self::shift_in(&mut self.samples, &self.chunk[..read]);
+            self.advance(read);
+            total += read;
+            if read < self.chunk.len() {
+                return total;
+            }
+        }
+    }
+
+    fn shift_in(samples: &mut [f32], new: &[f32]) {
+        let len = samples.len();
+        let n = new.len();
+        if n >= len {
+            samples.copy_from_slice(&new[n - len..]);
+        } else {
+            samples.copy_within(n.., 0);
+            samples[len - n..].copy_from_slice(new);
+        }
+    }
+
+    fn advance(&mut self, n: usize) {
+        self.filled = (self.filled + n).min(self.samples.len());
+        self.fresh += n;
+    }
+
+    /// True once a full window of real samples has arrived.
+    pub fn is_full(&self) -> bool {
+        self.filled == self.samples.len()
+    }
+
+    /// Samples pushed since the last [`Self::take_fresh`].
+    pub fn fresh(&self) -> usize {
+        self.fresh
+    }
+
+    pub fn take_fresh(&mut self) -> usize {
+        std::mem::take(&mut self.fresh)
+    }
+
+    pub fn as_slice(&self) -> &[f32] {
+        &self.samples
+    }
+}
+
+/// Sliding windows over an interleaved left/right stream, plus the mid
+/// (mono downmix) of the two.
+pub struct StereoWindow {
+    left: SlidingWindow,
+    right: SlidingWindow,
+    mid: SlidingWindow,
+    chunk: Vec<f32>,
+    scratch: [Vec<f32>; 3],
+    /// A left sample whose right half hasn't arrived yet.
+    pending: Option<f32>,
+}
+
+impl StereoWindow {
+    /// A window of `len` frames per channel.
+    pub fn new(len: usize) -> Self {
+        Self {
+            left: SlidingWindow::new(len),
+            right: SlidingWindow::new(len),
+            mid: SlidingWindow::new(len),
+            chunk: vec![0.0; len * 2],
+            scratch: [vec![0.0; len], vec![0.0; len], vec![0.0; len]],
+            pending: None,
+        }
+    }
+
+    /// Appends interleaved samples. Returns how many whole frames were added.
+    pub fn push_interleaved(&mut self, new: &[f32]) -> usize {
+        self.push_interleaved_with(new, |_, _, _| {})
+    }
+
+    fn push_interleaved_with(
+        &mut self,
+        mut new: &[f32],
+        mut on_chunk: impl FnMut(&[f32], &[f32], &[f32]),
+    ) -> usize {
+        let mut frames = 0;
+        if let Some(left) = self.pending.take() {
+            let Some((&right, rest)) = new.split_first() else {
+                self.pending = Some(left);
+                return 0;
+            };
+            self.push_pairs(&[left, right], &mut on_chunk);
+            frames += 1;
+            new = rest;
+        }
+        for chunk in new.chunks(self.scratch[0].len() * 2) {
+            let pairs = chunk.len() / 2;
+            self.push_pairs(&chunk[..pairs * 2], &mut on_chunk);
+            frames += pairs;
+            if chunk.len() % 2 == 1 {
+                self.pending = chunk.last().copied();
+            }
+        }
+        frames
+    }
+
+    /// At most one window's worth of whole frames.
+    fn push_pairs(
+        &mut self,
+        interleaved: &[f32],
+        on_chunk: &mut impl FnMut(&[f32], &[f32], &[f32]),
+    ) {
+        let frames = interleaved.len() / 2;
+        let [left, right, mid] = &mut self.scratch;
+        for (i, pair) in interleaved.chunks_exact(2).enumerate() {
+            left[i] = pair[0];
+            right[i] = pair[1];
+            mid[i] = (pair[0] + pair[1]) * 0.5;
+        }
+        if frames > 0 {
+            on_chunk(&left[..frames], &right[..frames], &mid[..frames]);
+        }
+        self.left.push_slice(&left[..frames]);
+        self.right.push_slice(&right[..frames]);
+        self.mid.push_slice(&mid[..frames]);
+    }
+
+    /// Moves everything `consumer` holds into the window, handing each
+    /// drained chunk to `on_chunk` as `(left, right, mid)`. Returns how many
+    /// frames were read.
+    pub fn fill_from(
+        &mut self,
+        consumer: &mut impl Consumer<Item = f32>,
+        mut on_chunk: impl FnMut(&[f32], &[f32], &[f32]),
+    ) -> usize {
+        let mut chunk = std::mem::take(&mut self.chunk);
+        let mut total = 0;
+        loop {
+            let read = consumer.pop_slice(&mut chunk);
+            total += self.push_interleaved_with(&chunk[..read], &mut on_chunk);
+            if read < chunk.len() {
+                break;
+            }
+        }
+        self.chunk = chunk;
+        total
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.mid.is_full()
+    }
+
+    /// Frames pushed since the last [`Self::take_fresh`].
+    pub fn fresh(&self) -> usize {
+        self.mid.fresh()
+    }
+
+    pub fn take_fresh(&mut self) -> usize {
+        self.left.take_fresh();
+        self.right.take_fresh();
+        self.mid.take_fresh()
+    }
+
+    pub fn left(&self) -> &[f32] {
+        self.left.as_slice()
+    }
+
+    pub fn right(&self) -> &[f32] {
+        self.right.as_slice()
+    }
+
+    pub fn mid(&self) -> &[f32] {
+        self.mid.as_slice()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ringbuf::traits::{Consumer, Producer};
+    use crate::audio::analysis::LoudnessMeter;
+    use ringbuf::traits::Producer;
 
     #[test]
     fn test_round_trip() {
@@ -50,4 +274,117 @@ mod tests {
         // Should only write up to capacity, no panic
         assert!(written <= 64);
     }
+
+    #[test]
+    fn test_sliding_window_keeps_latest() {
+        let mut window = SlidingWindow::new(8);
+        window.push_slice(&[1.0, 2.0, 3.0]);
+        assert!(!window.is_full());
+
+        window.push_slice(&[4.0, 5.0, 6.0, 7.0, 8.0, 9.0]);
+        assert!(window.is_full());
+        assert_eq!(window.as_slice(), &[2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0]);
+        assert_eq!(window.take_fresh(), 9);
+
+        // Longer than the window: only the tail survives
+        let long: Vec<f32> = (0..20).map(|i| i as f32).collect();
+        window.push_slice(&long);
+        assert_eq!(window.as_slice(), &long[12..]);
+        assert_eq!(window.fresh(), 20);
+    }
+
+    #[test]
+    fn test_sliding_window_drains_consumer() {
+        let (mut prod, mut cons) = AudioRingBuffer::new(64).split();
+        let data: Vec<f32> = (0..40).map(|i| i as f32).collect();
+        prod.push_slice(&data);
+
+        // Window shorter than what's queued: drains in several chunks
+        let mut window = SlidingWindow::new(16);
+        let mut seen = Vec::new();
+        let read = window.fill_from(&mut cons, |chunk| seen.extend_from_slice(chunk));
+        assert_eq!(read, 40);
+        assert_eq!(window.as_slice(), &data[24..]);
+        assert_eq!(seen, data);
+        assert_eq!(window.fill_from(&mut cons, |_| {}), 0);
+    }
+
+    #[test]
+    fn test_stereo_window_splits_channels() {
+        let mut window = StereoWindow::new(4);
+        // Odd-length pushes leave half a frame for the next one
+        assert_eq!(window.push_interleaved(&[1.0, -1.0, 2.0]), 1);
+        assert_eq!(window.push_interleaved(&[-2.0, 3.0, -3.0, 4.0]), 2);
+        assert!(!window.is_full());
+        assert_eq!(window.push_interleaved(&[6.0]), 1);
+        assert!(window.is_full());
+        assert_eq!(window.left(), &[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(window.right(), &[-1.0, -2.0, -3.0, 6.0]);
+        assert_eq!(window.mid(), &[0.0, 0.0, 0.0, 5.0]);
+        assert_eq!(window.take_fresh(), 4);
+
+        let (mut prod, mut cons) = AudioRingBuffer::new(64).split();
+        let data: Vec<f32> = (0..40).map(|i| i as f32).collect();
+        prod.push_slice(&data);
+        assert_eq!(window.fill_from(&mut cons, |_, _, _| {}), 20);
+        assert_eq!(window.left(), &[32.0, 34.0, 36.0, 38.0]);
+        assert_eq!(window.right(), &[33.0, 35.0, 37.0, 39.0]);
+        assert_eq!(window.fresh(), 20);
+    }
+
+    #[test]
+    fn test_chunks_meter_every_drained_sample() {
+        // Half a second queued against a 1024-frame window: the meter must
+        // see all of it, not just the frames left in the window
+        let rate = 48_000;
+        let frames = rate as usize / 2;
+        let signal: Vec<f32> = (0..frames).map(|i| (i as f32 * 0.13).sin() * 0.5).collect();
+        let (mut prod, mut cons) = AudioRingBuffer::new(frames).split();
+        prod.push_slice(&signal);
+
+        let mut window = SlidingWindow::new(1024);
+        let mut meter = LoudnessMeter::new(rate);
+        let mut metered = 0;
+        let read = window.fill_from(&mut cons, |chunk| {
+            metered += chunk.len();
+            meter.process(chunk);
+        });
+        assert_eq!(read, frames);
+        assert_eq!(metered, frames);
+
+        let mut expected = LoudnessMeter::new(rate);
+        expected.process(&signal);
+        assert_eq!(meter.loudness(), expected.loudness());
+    }
+
+    #[test]
+    fn test_stereo_chunks_meter_every_drained_frame() {
+        let rate = 48_000;
+        let frames = rate as usize / 2;
+        let left: Vec<f32> = (0..frames).map(|i| (i as f32 * 0.13).sin() * 0.5).collect();
+        let right: Vec<f32> = (0..frames)
+            .map(|i| (i as f32 * 0.07).sin() * 0.25)
+            .collect();
+        let interleaved: Vec<f32> = left
+            .iter()
+            .zip(&right)
+            .flat_map(|(&l, &r)| [l, r])
+            .collect();
+        let (mut prod, mut cons) = AudioRingBuffer::new(interleaved.len()).split();
+        prod.push_slice(&interleaved);
+
+        let mut window = StereoWindow::new(1024);
+        let mut meter = LoudnessMeter::new(rate);
+        let mut metered = 0;
+        let read = window.fill_from(&mut cons, |l, r, _| {
+            metered += l.len();
+            meter.process_stereo(l, r);
+        });
+        assert_eq!(read, frames);
+        assert_eq!(metered, frames);
+
+        let mut expected = LoudnessMeter::new(rate);
+        expected.process_stereo(&left, &right);
+        assert_eq!(meter.loudness(), expected.loudness());
+    }
 }
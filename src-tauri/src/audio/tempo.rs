@@ -0,0 +1,279 @@
+/// Rate the onset-strength envelope is resampled to, in Hz. Frames arrive at
+/// whatever rate the analysis runs; autocorrelation needs an even grid.
+const ENVELOPE_RATE: f64 = 100.0;
+/// Envelope kept for autocorrelation, in seconds.
+const WINDOW_SECS: f64 = 10.0;
+/// Envelope needed before the first estimate, in seconds.
+const MIN_HISTORY_SECS: f64 = 4.0;
+/// Re-estimate after this much new envelope, in seconds.
+const UPDATE_SECS: f64 = 0.5;
+/// Gap in timestamps (a seek or a stall) after which history is dropped.
+const MAX_GAP_SECS: f64 = 1.0;
+/// Candidate tempo range.
+const MIN_BPM: f64 = 30.0;
+const MAX_BPM: f64 = 300.0;
+/// Longest lag autocorrelated, in seconds; the comb reaches out this far.
+const MAX_LAG_SECS: f64 = 4.0;
+/// Width of the box filter run over the envelope, in grid samples. Onsets
+/// only land on frame boundaries, so at 60 fps they jitter by up to 17 ms;
+/// smoothing over about 50 ms keeps that from splitting ACF peaks.
+const SMOOTHING: usize = 5;
+/// Multiples of a candidate period the comb sums over.
+const COMB_HARMONICS: usize = 4;
+/// A candidate's own autocorrelation must reach this fraction of its comb
+/// mean, so a period with nothing happening on it can't win on the
+/// strength of its multiples.
+const MIN_FUNDAMENTAL: f32 = 0.5;
+/// Centre and width (in octaves) of the log-tempo prior that settles
+/// octave ambiguity towards the tempo people tap along to.
+const PRIOR_BPM: f64 = 120.0;
+const PRIOR_OCTAVES: f64 = 1.0;
+
+/// Tempo from the autocorrelation of an onset-strength envelope.
+///
+/// Each candidate period is scored by a comb over its first few multiples,
+/// so the true period outscores its fractions, then weighted by a prior so
+/// half- and double-time readings fall to the likelier metrical level.
+/// Buffers are sized up front.
+pub struct TempoEstimator {
+    /// Ring of envelope samples; `write` is the next slot.
+    envelope: Vec<f32>,
+    write: usize,
+    filled: usize,
+    /// Envelope unrolled oldest first, mean removed.
+    linear: Vec<f32>,
+    acf: Vec<f32>,
+    last: Option<(f64, f32)>,
+    next_grid: f64,
+    since_update: usize,
+    bpm: f32,
+    confidence: f32,
+}
+
+impl Default for TempoEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TempoEstimator {
+    pub fn new() -> Self {
+        let len = (WINDOW_SECS * ENVELOPE_RATE) as usize;
+        let max_lag = (MAX_LAG_SECS * ENVELOPE_RATE) as usize;
+        Self {
+            envelope: vec![0.0; len],
+            write: 0,
+            filled: 0,
+            linear: vec![0.0; len],
+            acf: vec![0.0; max_lag + 1],
+            last: None,
+            next_grid: 0.0,
+            since_update: 0,
+            bpm: 0.0,
+            confidence: 0.0,
+        }
+    }
+
+    /// Drops all history, as after a seek.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Latest estimate in BPM; 0 until enough has been heard.
+    pub fn bpm(&self) -> f32 {
+        self.bpm
+    }
+
+    /// Normalized autocorrelation at the chosen period, 0..1. Near 1 for a
+    /// steady pulse, near 0 for beatless material.
+    pub fn confidence(&self) -> f32 {
+        self.confidence
+    }
+
+    /// Adds one frame's onset strength at `timestamp` seconds.
+    pub fn push(&mut self, timestamp: f64, strength: f32) {
+        let Some((last_time, last_value)) = self.last else {
+            self.last = Some((timestamp, strength));
+            self.next_grid = timestamp;
+            return;
+        };
+        if timestamp < last_time || timestamp - last_time > MAX_GAP_SECS {
+            self.reset();
+            self.push(timestamp, strength);
+            return;
+        }
+
+        // Linear interpolation onto the grid between the last two frames
+        let step = 1.0 / ENVELOPE_RATE;
+        let span = timestamp - last_time;
+        while self.next_grid <= timestamp {
+            let t = if span > 0.0 {
+                ((self.next_grid - last_time) / span) as f32
+            } else {
+                1.0
+            };
+            self.envelope[self.write] = last_value + (strength - last_value) * t;
+            self.write = (self.write + 1) % self.envelope.len();
+            self.filled = (self.filled + 1).min(self.envelope.len());
+            self.since_update += 1;
+            self.next_grid += step;
+        }
+        self.last = Some((timestamp, strength));
+
+        if self.filled as f64 >= MIN_HISTORY_SECS * ENVELOPE_RATE
+            && self.since_update as f64 >= UPDATE_SECS * ENVELOPE_RATE
+        {
+            self.since_update = 0;
+            self.estimate();
+        }
+    }
+
+    fn estimate(&mut self) {
+        let n = self.filled;
+        let len = self.envelope.len();
+        let start = (self.write + len - n) % len;
+        for (i, x) in self.linear[..n].iter_mut().enumerate() {
+            *x = self.envelope[(start + i) % len];
+        }
+        // Running box filter, in place: `window` holds the raw samples it covers
+        let mut sum = 0.0;
+        let mut window = [0.0f32; SMOOTHING];
+        for i in 0..n {
+            let raw = self.linear[i];
+            sum += raw - window[i % SMOOTHING];
+            window[i % SMOOTHING] = raw;
+            self.linear[i] = sum / SMOOTHING as f32;
+        }
+        let mean = self.linear[..n].iter().sum::<f32>() / n as f32;
+        self.linear[..n].iter_mut().for_each(|x| *x -= mean);
+
+        // Unbiased autocorrelation, so long lags aren't penalized for
+        // overlapping less
+        let max_lag = (self.acf.len() - 1).min(n / 2);
+        let x = &self.linear[..n];
+        for lag in 0..=max_lag {
+            let sum: f32 = x[..n - lag].iter().zip(&x[lag..]).map(|(a, b)| a * b).sum();
+            self.acf[lag] = sum / (n - lag) as f32;
+        }
+        let energy = self.acf[0];
+        if energy <= 1e-12 {
+            self.confidence = 0.0;
+            return;
+        }
+
+        // Candidate periods step in quarter samples so the comb's later
+        // multiples land on the right lag even when the period doesn't
+        let min_period = 60.0 * ENVELOPE_RATE / MAX_BPM;
+        let max_period = (60.0 * ENVELOPE_RATE / MIN_BPM).min((max_lag - 1) as f64);
+        let acf_at = |lag: f64| {
+            let i = lag as usize;
+            let frac = (lag - i as f64) as f32;
+            self.acf[i] * (1.0 - frac) + self.acf[(i + 1).min(max_lag)] * frac
+        };
+        let mut best: Option<(f64, f64)> = None;
+        let mut period = min_period.max(1.0);
+        while period <= max_period {
+            let (mut sum, mut count) = (0.0, 0);
+            for k in 1..=COMB_HARMONICS {
+                if k as f64 * period > max_lag as f64 {
+                    break;
+                }
+                sum += acf_at(k as f64 * period);
+                count += 1;
+            }
+            let comb = sum / count as f32;
+            if acf_at(period) >= MIN_FUNDAMENTAL * comb {
+                let bpm = 60.0 * ENVELOPE_RATE / period;
+                let octaves = (bpm / PRIOR_BPM).log2() / PRIOR_OCTAVES;
+                let score = comb as f64 * (-0.5 * octaves * octaves).exp();
+                if best.is_none_or(|(_, s)| score > s) {
+                    best = Some((period, score));
+                }
+            }
+            period += 0.25;
+        }
+        let Some((period, score)) = best else {
+            return;
+        };
+        if score <= 0.0 {
+            self.confidence = 0.0;
+            return;
+        }
+
+        // Parabolic interpolation around the nearest whole lag
+        let lag = (period.round() as usize).clamp(1, max_lag - 1);
+        let (a, b, c) = (self.acf[lag - 1], self.acf[lag], self.acf[lag + 1]);
+        let denom = a - 2.0 * b + c;
+        let offset = if denom.abs() > 1e-12 && b >= a && b >= c {
+            (0.5 * (a - c) / denom).clamp(-0.5, 0.5) as f64
+        } else {
+            period - lag as f64
+        };
+        self.bpm = (60.0 * ENVELOPE_RATE / (lag as f64 + offset)) as f32;
+        self.confidence = (acf_at(period) / energy).clamp(0.0, 1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `secs` of 60 fps frames with `pulse(t)` as the onset strength.
+    fn run(secs: f64, mut pulse: impl FnMut(f64) -> f32) -> TempoEstimator {
+        let mut tempo = TempoEstimator::new();
+        for frame in 0..(secs * 60.0) as usize {
+            let t = frame as f64 / 60.0;
+            tempo.push(t, pulse(t));
+        }
+        tempo
+    }
+
+    /// 1 on the frame nearest each multiple of `period`, 0 elsewhere.
+    fn clicks(t: f64, period: f64) -> f32 {
+        let phase = t % period;
+        if phase < 1.0 / 60.0 - 1e-9 {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    #[test]
+    fn test_steady_pulses() {
+        for bpm in [50.0, 90.0, 120.0, 128.0, 160.0] {
+            let tempo = run(12.0, |t| clicks(t, 60.0 / bpm));
+            assert!(
+                (tempo.bpm() - bpm as f32).abs() < 1.5,
+                "{bpm}: {}",
+                tempo.bpm()
+            );
+            assert!(
+                tempo.confidence() > 0.5,
+                "{bpm}: confidence {}",
+                tempo.confidence()
+            );
+        }
+    }
+
+    #[test]
+    fn test_eighth_note_hats_read_as_quarters() {
+        // Kick on the beat at 120, softer hats between: the pulse repeats
+        // every 0.25 s but the beat is every 0.5 s
+        let tempo = run(12.0, |t| clicks(t, 0.5) + 0.6 * clicks(t + 0.25, 0.5));
+        assert!((tempo.bpm() - 120.0).abs() < 1.5, "{}", tempo.bpm());
+
+        // Kick only on 1 and 3 with a snare between reads as the quarter too
+        let tempo = run(12.0, |t| clicks(t, 1.0) + 0.8 * clicks(t + 0.5, 1.0));
+        assert!((tempo.bpm() - 120.0).abs() < 1.5, "{}", tempo.bpm());
+    }
+
+    #[test]
+    fn test_noise_has_low_confidence() {
+        let mut rng = fastrand::Rng::with_seed(5);
+        let tempo = run(12.0, |_| rng.f32());
+        assert!(tempo.confidence() < 0.2, "{}", tempo.confidence());
+
+        let silent = run(12.0, |_| 0.0);
+        assert_eq!((silent.bpm(), silent.confidence()), (0.0, 0.0));
+    }
+}
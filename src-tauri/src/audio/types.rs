@@ -1,17 +1,162 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use super::analysis::{AnalysisResult, BandEnergies, Loudness, Pitch, StereoResult};
+use super::beat::{BeatPosition, Onsets};
+use super::key::{estimate_chord, Chord, KeyEstimate};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AudioFrame {
+    /// Linear bins or bands in 0..1, per `AudioConfig::spectrum_layout` and
+    /// `AudioConfig::spectrum_scale`.
     pub spectrum: Vec<f32>,
+    /// `spectrum` through the attack/release envelope.
+    #[serde(default)]
+    pub smoothed: Vec<f32>,
+    /// Peak-hold markers over `spectrum`, falling under gravity.
+    #[serde(default)]
+    pub peaks: Vec<f32>,
     pub waveform: Vec<f32>,
     pub rms: f32,
+    /// EBU R128 loudness of the stream so far.
+    #[serde(default)]
+    pub loudness: Loudness,
+    /// Spectral centroid in Hz.
     pub centroid: f32,
+    /// Frequency in Hz below which 85% of the spectral energy lies.
+    #[serde(default)]
+    pub rolloff: f32,
+    /// Spectral flatness, 0 (tonal) to 1 (noise-like).
+    #[serde(default)]
+    pub flatness: f32,
+    /// Spectral crest factor, peak over mean magnitude.
+    #[serde(default)]
+    pub crest: f32,
+    /// Spectral bandwidth around the centroid, in Hz.
+    #[serde(default)]
+    pub bandwidth: f32,
+    /// Spectral slope per kHz; negative when the highs roll off.
+    #[serde(default)]
+    pub slope: f32,
+    #[serde(default)]
+    pub band_energy: BandEnergies,
     pub flux: f32,
     pub zcr: f32,
+    /// Mel-frequency cepstral coefficients; empty unless `AudioConfig::mfcc`.
+    #[serde(default)]
+    pub mfcc: Vec<f32>,
+    /// Energy per pitch class, C first, loudest at 1; empty unless
+    /// `AudioConfig::chroma`.
+    #[serde(default)]
+    pub chroma: Vec<f32>,
+    /// Key over the last half minute or so; needs `AudioConfig::chroma`.
+    #[serde(default)]
+    pub key: Option<KeyEstimate>,
+    /// Triad this frame sounds closest to; needs `AudioConfig::chroma`.
+    #[serde(default)]
+    pub chord: Option<Chord>,
+    /// Fundamental of monophonic input; needs `AudioConfig::pitch`.
+    #[serde(default)]
+    pub pitch: Option<Pitch>,
+    /// Per-channel spectra and stereo image; `None` with
+    /// `AudioConfig::mono_downmix`.
+    #[serde(default)]
+    pub stereo: Option<StereoResult>,
     pub beat: bool,
     pub bpm: f32,
+    /// How sure the tempo estimate is, 0..1; low values mean `bpm` comes
+    /// from onset intervals rather than autocorrelation.
+    #[serde(default)]
+    pub tempo_confidence: f32,
+    /// `bpm` is a manual lock and beats come from its clock grid.
+    #[serde(default)]
+    pub bpm_locked: bool,
+    /// Kick, snare and hi-hat hits in this frame.
+    #[serde(default)]
+    pub onsets: Onsets,
+    /// Beat grid, bar and phrase position; `None` until a tempo
+    /// and a first beat are known.
+    #[serde(default)]
+    pub beat_position: Option<BeatPosition>,
     pub timestamp: f64,
+    /// Rate of the analyzed audio; 0 on sentinel frames.
+    #[serde(default)]
+    pub sample_rate: u32,
+    /// Width of one FFT bin in Hz. With the linear layout `spectrum[i]` is
+    /// centred on `i * bin_hz`; band layouts don't use it.
+    #[serde(default)]
+    pub bin_hz: f32,
+}
+
+impl AudioFrame {
+    pub fn from_analysis(result: &AnalysisResult, beat: bool, bpm: f32, timestamp: f64) -> Self {
+        Self {
+            spectrum: result.display.clone(),
+            smoothed: result.smoothed.clone(),
+            peaks: result.peaks.clone(),
+            waveform: result.waveform.clone(),
+            rms: result.rms,
+            loudness: Loudness::default(),
+            centroid: result.centroid,
+            rolloff: result.rolloff,
+            flatness: result.flatness,
+            crest: result.crest,
+            bandwidth: result.bandwidth,
+            slope: result.slope,
+            band_energy: result.band_energy,
+            flux: result.flux,
+            zcr: result.zcr,
+            mfcc: result.mfcc.clone(),
+            chroma: result.chroma.clone(),
+            key: None,
+            chord: estimate_chord(&result.chroma),
+            pitch: (result.f0 > 0.0).then(|| Pitch::from_frequency(result.f0, result.pitch_confidence)),
+            stereo: None,
+            beat,
+            bpm,
+            tempo_confidence: 0.0,
+            bpm_locked: false,
+            onsets: Onsets::default(),
+            beat_position: None,
+            timestamp,
+            sample_rate: result.sample_rate,
+            bin_hz: result.bin_hz,
+        }
+    }
+}
+
+/// Emitted as [`PLAYBACK_FINISHED_EVENT`] when file playback reaches the end.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaybackFinished {
+    pub path: String,
+    pub duration_secs: f64,
+    /// Loudness of the track as it played; seeking skews it.
+    #[serde(default)]
+    pub loudness: Option<Loudness>,
+}
+
+pub const PLAYBACK_FINISHED_EVENT: &str = "playback-finished";
+
+/// Emitted as [`TRACK_CHANGED_EVENT`] when a playlist track becomes audible.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackChanged {
+    pub id: u64,
+    pub index: Option<usize>,
+    pub path: String,
+    pub duration_secs: f64,
+}
+
+pub const TRACK_CHANGED_EVENT: &str = "track-changed";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaybackPosition {
+    pub path: String,
+    pub position_secs: f64,
+    pub duration_secs: f64,
+    pub finished: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +167,66 @@ pub struct AudioDevice {
     pub is_input: bool,
 }
 
+/// How the spectrum sent to the frontend is laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SpectrumLayout {
+    /// Raw FFT bins, `fft_size / 2` of them.
+    #[default]
+    Linear,
+    /// `band_count` log-spaced bands.
+    Log,
+    /// `band_count` bands evenly spaced on the mel scale.
+    Mel,
+    /// Standard 1/3-octave bands; `band_count` is ignored.
+    ThirdOctave,
+}
+
+/// How spectrum values are scaled into 0..1 for display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SpectrumScale {
+    /// Divide each frame by its own peak. Always fills the range, but
+    /// loses absolute level.
+    #[default]
+    Normalized,
+    /// Map `db_floor..db_ceiling` dBFS onto 0..1.
+    Decibel,
+}
+
+/// Frequency range an onset detector listens to.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OnsetBand {
+    pub low_hz: f32,
+    pub high_hz: f32,
+}
+
+impl OnsetBand {
+    pub const fn new(low_hz: f32, high_hz: f32) -> Self {
+        Self { low_hz, high_hz }
+    }
+}
+
+/// Bands for the kick, snare and hi-hat onset detectors.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OnsetBands {
+    pub kick: OnsetBand,
+    pub snare: OnsetBand,
+    pub hat: OnsetBand,
+}
+
+impl Default for OnsetBands {
+    fn default() -> Self {
+        Self {
+            kick: OnsetBand::new(40.0, 150.0),
+            snare: OnsetBand::new(200.0, 2500.0),
+            hat: OnsetBand::new(6000.0, 16000.0),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AudioConfig {
@@ -30,12 +235,89 @@ pub struct AudioConfig {
     pub target_fps: u32,
     #[serde(default = "default_sensitivity")]
     pub sensitivity: f32,
+    /// Output device for file playback; `None` uses the system default.
+    #[serde(default)]
+    pub output_device_name: Option<String>,
+    /// Samples between analysis frames, so windows overlap by
+    /// `fft_size - hop_size`. Offline analysis steps by exactly this; live
+    /// analysis waits for at least this many new samples but never runs faster
+    /// than `target_fps`. `None` derives the hop from `target_fps`.
+    #[serde(default)]
+    pub hop_size: Option<usize>,
+    #[serde(default)]
+    pub spectrum_layout: SpectrumLayout,
+    /// Number of bands for the log and mel layouts.
+    #[serde(default = "default_band_count")]
+    pub band_count: usize,
+    #[serde(default)]
+    pub spectrum_scale: SpectrumScale,
+    /// Level shown as empty in the decibel scale, in dBFS.
+    #[serde(default = "default_db_floor")]
+    pub db_floor: f32,
+    /// Level shown as full in the decibel scale, in dBFS.
+    #[serde(default)]
+    pub db_ceiling: f32,
+    /// Slowly adjust gain so the decibel scale follows the material's level.
+    #[serde(default)]
+    pub auto_gain: bool,
+    /// Rise time constant of the smoothed spectrum, in ms.
+    #[serde(default = "default_attack_ms")]
+    pub attack_ms: f32,
+    /// Fall time constant of the smoothed spectrum, in ms.
+    #[serde(default = "default_release_ms")]
+    pub release_ms: f32,
+    /// How long peak markers stay put before falling, in ms.
+    #[serde(default = "default_peak_hold_ms")]
+    pub peak_hold_ms: f32,
+    /// How long a released peak takes to fall from full scale to zero, in ms.
+    #[serde(default = "default_peak_fall_ms")]
+    pub peak_fall_ms: f32,
+    /// Compute mel-frequency cepstral coefficients each frame.
+    #[serde(default)]
+    pub mfcc: bool,
+    /// Compute a 12-bin chroma vector each frame.
+    #[serde(default)]
+    pub chroma: bool,
+    /// Track the fundamental of monophonic input (voice, lead lines).
+    #[serde(default)]
+    pub pitch: bool,
+    /// Analyze only the mono downmix, skipping per-channel spectra and
+    /// stereo image measures.
+    #[serde(default)]
+    pub mono_downmix: bool,
+    /// Frequency ranges of the per-drum onset detectors.
+    #[serde(default)]
+    pub onset_bands: OnsetBands,
 }
 
 fn default_sensitivity() -> f32 {
     1.0
 }
 
+fn default_band_count() -> usize {
+    64
+}
+
+fn default_db_floor() -> f32 {
+    -80.0
+}
+
+fn default_attack_ms() -> f32 {
+    30.0
+}
+
+fn default_release_ms() -> f32 {
+    150.0
+}
+
+fn default_peak_hold_ms() -> f32 {
+    500.0
+}
+
+fn default_peak_fall_ms() -> f32 {
+    1500.0
+}
+
 impl AudioConfig {
     /// Validates config values, clamping to safe ranges.
     pub fn validated(mut self) -> Self {
@@ -47,8 +329,40 @@ impl AudioConfig {
         self.target_fps = self.target_fps.clamp(1, 120);
         // Sensitivity must be in [0.5, 2.0]
         self.sensitivity = self.sensitivity.clamp(0.5, 2.0);
+        // Hop must be in [1, fft_size]
+        self.hop_size = self.hop_size.filter(|&h| h > 0).map(|h| h.min(self.fft_size));
+        // Band count must be in [8, 512]
+        self.band_count = self.band_count.clamp(8, 512);
+        // dB range must be in [-160, 20] and at least 10 dB wide
+        self.db_floor = self.db_floor.clamp(-160.0, 10.0);
+        self.db_ceiling = self.db_ceiling.clamp(self.db_floor + 10.0, 20.0);
+        // Envelope times must be in [0, 10000] ms
+        for ms in [
+            &mut self.attack_ms,
+            &mut self.release_ms,
+            &mut self.peak_hold_ms,
+            &mut self.peak_fall_ms,
+        ] {
+            *ms = ms.clamp(0.0, 10_000.0);
+        }
+        // Onset bands must be in [0, 24000] Hz and at least 10 Hz wide
+        for band in [
+            &mut self.onset_bands.kick,
+            &mut self.onset_bands.snare,
+            &mut self.onset_bands.hat,
+        ] {
+            band.low_hz = band.low_hz.clamp(0.0, 23_990.0);
+            band.high_hz = band.high_hz.clamp(band.low_hz + 10.0, 24_000.0);
+        }
         self
     }
+
+    /// Hop in samples at `sample_rate`.
+    pub fn hop_for_rate(&self, sample_rate: u32) -> usize {
+        self.hop_size
+            .unwrap_or((sample_rate / self.target_fps.max(1)) as usize)
+            .max(1)
+    }
 }
 
 impl Default for AudioConfig {
@@ -58,6 +372,23 @@ impl Default for AudioConfig {
             fft_size: 2048,
             target_fps: 60,
             sensitivity: 1.0,
+            output_device_name: None,
+            hop_size: None,
+            spectrum_layout: SpectrumLayout::Linear,
+            band_count: default_band_count(),
+            spectrum_scale: SpectrumScale::Normalized,
+            db_floor: default_db_floor(),
+            db_ceiling: 0.0,
+            auto_gain: false,
+            attack_ms: default_attack_ms(),
+            release_ms: default_release_ms(),
+            peak_hold_ms: default_peak_hold_ms(),
+            peak_fall_ms: default_peak_fall_ms(),
+            mfcc: false,
+            chroma: false,
+            pitch: false,
+            mono_downmix: false,
+            onset_bands: OnsetBands::default(),
         }
     }
 }
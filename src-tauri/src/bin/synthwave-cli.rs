@@ -0,0 +1,767 @@
+//! Headless batch analysis: runs SynthWave's analysis pipeline over audio
+//! files and writes per-frame features plus a per-file summary.
+
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+use synthwave_lib::audio::analysis::{BandEnergies, Loudness, Pitch, StereoResult};
+use synthwave_lib::audio::beat::{BeatPosition, Onsets};
+use synthwave_lib::audio::key::{Chord, KeyEstimate};
+use synthwave_lib::audio::offline::{self, OfflineAnalysis};
+use synthwave_lib::audio::types::{
+    AudioConfig, AudioFrame, OnsetBand, SpectrumLayout, SpectrumScale,
+};
+
+const USAGE: &str = "\
+Usage: synthwave-cli [OPTIONS] <FILE|DIR>...
+
+Analyzes audio files (directories are searched recursively) and writes
+per-frame features and a per-file summary.
+
+Options:
+  -f, --format <jsonl|csv>  Per-frame output format [default: jsonl]
+  -o, --out-dir <DIR>       Write <name>.frames.<ext> and <name>.summary.json
+                            per file, mirroring the subdirectories of DIR
+                            inputs. Files sharing a name keep their
+                            extension in it. Without it, frames go to stdout
+                            and summaries to stderr as JSON Lines. CSV on
+                            stdout is one table, so its linear spectrum
+                            columns need every file at the same sample rate.
+      --summary-only        Skip per-frame output
+      --fft-size <N>        FFT size, power of two in 256..=16384 [default: 2048]
+      --fps <N>             Analysis frames per second [default: 60]
+      --hop-size <N>        Samples between frames; overrides --fps
+      --bands <LAYOUT>      Spectrum layout: linear, log, mel or octave
+                            (1/3-octave) [default: linear]
+      --band-count <N>      Bands for the log and mel layouts [default: 64]
+      --scale <MODE>        Spectrum scale: normalized (per frame) or db
+                            [default: normalized]
+      --db-range <LO:HI>    dBFS range for the db scale [default: -80:0]
+      --auto-gain           Slowly adjust gain under the db scale
+      --mfcc                Add 13 MFCCs to each frame
+      --chroma              Add a 12-bin chroma vector, key and chord to each frame
+      --pitch               Track the fundamental of monophonic input
+      --mono                Analyze the mono downmix only, without stereo image
+      --sensitivity <X>     Beat detection sensitivity, 0.5..=2.0 [default: 1.0]
+      --kick <LO:HI>        Kick onset band in Hz [default: 40:150]
+      --snare <LO:HI>       Snare onset band in Hz [default: 200:2500]
+      --hat <LO:HI>         Hi-hat onset band in Hz [default: 6000:16000]
+  -h, --help                Print this help
+";
+
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "wav", "flac", "ogg", "m4a", "aac"];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    JsonLines,
+    Csv,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::JsonLines => "jsonl",
+            Format::Csv => "csv",
+        }
+    }
+}
+
+struct Options {
+    inputs: Vec<PathBuf>,
+    format: Format,
+    out_dir: Option<PathBuf>,
+    summary_only: bool,
+    config: AudioConfig,
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        inputs: Vec::new(),
+        format: Format::JsonLines,
+        out_dir: None,
+        summary_only: false,
+        config: AudioConfig::default(),
+    };
+
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{name} needs a value"));
+        match arg.as_str() {
+            "-h" | "--help" => return Err(String::new()),
+            "-f" | "--format" => {
+                options.format = match value(&arg)?.as_str() {
+                    "jsonl" | "json" => Format::JsonLines,
+                    "csv" => Format::Csv,
+                    other => return Err(format!("Unknown format '{other}'")),
+                }
+            }
+            "-o" | "--out-dir" => options.out_dir = Some(PathBuf::from(value(&arg)?)),
+            "--summary-only" => options.summary_only = true,
+            "--fft-size" => {
+                options.config.fft_size = value(&arg)?
+                    .parse()
+                    .map_err(|_| "--fft-size must be a number".to_string())?
+            }
+            "--fps" => {
+                options.config.target_fps = value(&arg)?
+                    .parse()
+                    .map_err(|_| "--fps must be a number".to_string())?
+            }
+            "--hop-size" => {
+                let hop = value(&arg)?
+                    .parse()
+                    .map_err(|_| "--hop-size must be a number".to_string())?;
+                options.config.hop_size = Some(hop)
+            }
+            "--bands" => {
+                options.config.spectrum_layout = match value(&arg)?.as_str() {
+                    "linear" => SpectrumLayout::Linear,
+                    "log" => SpectrumLayout::Log,
+                    "mel" => SpectrumLayout::Mel,
+                    "octave" => SpectrumLayout::ThirdOctave,
+                    other => return Err(format!("Unknown band layout '{other}'")),
+                }
+            }
+            "--band-count" => {
+                options.config.band_count = value(&arg)?
+                    .parse()
+                    .map_err(|_| "--band-count must be a number".to_string())?
+            }
+            "--scale" => {
+                options.config.spectrum_scale = match value(&arg)?.as_str() {
+                    "normalized" => SpectrumScale::Normalized,
+                    "db" => SpectrumScale::Decibel,
+                    other => return Err(format!("Unknown scale '{other}'")),
+                }
+            }
+            "--db-range" => {
+                let range = value(&arg)?;
+                let (lo, hi) = range
+                    .split_once(':')
+                    .and_then(|(lo, hi)| Some((lo.parse().ok()?, hi.parse().ok()?)))
+                    .ok_or_else(|| format!("--db-range must look like -80:0, got '{range}'"))?;
+                options.config.db_floor = lo;
+                options.config.db_ceiling = hi;
+            }
+            "--auto-gain" => options.config.auto_gain = true,
+            "--mfcc" => options.config.mfcc = true,
+            "--chroma" => options.config.chroma = true,
+            "--pitch" => options.config.pitch = true,
+            "--mono" => options.config.mono_downmix = true,
+            "--sensitivity" => {
+                options.config.sensitivity = value(&arg)?
+                    .parse()
+                    .map_err(|_| "--sensitivity must be a number".to_string())?
+            }
+            "--kick" | "--snare" | "--hat" => {
+                let range = value(&arg)?;
+                let (lo, hi) = range
+                    .split_once(':')
+                    .and_then(|(lo, hi)| Some((lo.parse().ok()?, hi.parse().ok()?)))
+                    .ok_or_else(|| format!("{arg} must look like 40:150, got '{range}'"))?;
+                let bands = &mut options.config.onset_bands;
+                let band = match arg.as_str() {
+                    "--kick" => &mut bands.kick,
+                    "--snare" => &mut bands.snare,
+                    _ => &mut bands.hat,
+                };
+                *band = OnsetBand::new(lo, hi);
+            }
+            s if s.starts_with('-') && s.len() > 1 => return Err(format!("Unknown option '{s}'")),
+            _ => options.inputs.push(PathBuf::from(arg)),
+        }
+    }
+
+    if options.inputs.is_empty() {
+        return Err("No input files given".into());
+    }
+    options.config = options.config.validated();
+    Ok(options)
+}
+
+/// An audio file to analyze and the name its outputs get under the output
+/// directory.
+struct Input {
+    path: PathBuf,
+    /// Path relative to the directory it was found in (or the bare file
+    /// name), without the extension unless that's needed to tell it apart.
+    name: PathBuf,
+}
+
+/// Expands directories into the audio files beneath them, sorted by path.
+fn collect_files(inputs: &[PathBuf]) -> Vec<Input> {
+    fn walk(dir: &Path, out: &mut Vec<PathBuf>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            eprintln!("Cannot read directory {}", dir.display());
+            return;
+        };
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+        paths.sort();
+        for path in paths {
+            if path.is_dir() {
+                walk(&path, out);
+            } else if is_audio_file(&path) {
+                out.push(path);
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    for input in inputs {
+        if input.is_dir() {
+            let mut found = Vec::new();
+            walk(input, &mut found);
+            for path in found {
+                let name = path.strip_prefix(input).unwrap_or(&path).to_path_buf();
+                files.push(Input { path, name });
+            }
+        } else {
+            let name = input
+                .file_name()
+                .map_or_else(|| "output".into(), PathBuf::from);
+            files.push(Input {
+                path: input.clone(),
+                name,
+            });
+        }
+    }
+    files
+}
+
+/// Drops the extension from output names where that leaves them unique.
+/// Two inputs that would still write the same outputs are an error rather
+/// than a silent overwrite.
+fn name_outputs(files: &mut [Input]) -> Result<(), String> {
+    let stems: Vec<PathBuf> = files.iter().map(|f| f.name.with_extension("")).collect();
+    let clashes = |stem: &PathBuf| stems.iter().filter(|s| *s == stem).count() > 1;
+    let names: Vec<PathBuf> = files
+        .iter()
+        .zip(&stems)
+        .map(|(f, stem)| {
+            if clashes(stem) {
+                f.name.clone()
+            } else {
+                stem.clone()
+            }
+        })
+        .collect();
+
+    for (i, name) in names.iter().enumerate() {
+        if let Some(j) = names[..i].iter().position(|n| n == name) {
+            return Err(format!(
+                "{} and {} would write the same outputs",
+                files[j].path.display(),
+                files[i].path.display()
+            ));
+        }
+    }
+    for (file, name) in files.iter_mut().zip(names) {
+        file.name = name;
+    }
+    Ok(())
+}
+
+/// `dir/name` with `suffix` appended to the file name.
+fn output_path(dir: &Path, name: &Path, suffix: &str) -> PathBuf {
+    let mut path = dir.join(name).into_os_string();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| AUDIO_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// The per-frame fields written out; the waveform is left out on purpose.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FrameRecord<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<&'a str>,
+    timestamp: f64,
+    rms: f32,
+    loudness: Loudness,
+    centroid: f32,
+    rolloff: f32,
+    flatness: f32,
+    crest: f32,
+    bandwidth: f32,
+    slope: f32,
+    band_energy: BandEnergies,
+    flux: f32,
+    zcr: f32,
+    beat: bool,
+    bpm: f32,
+    tempo_confidence: f32,
+    onsets: Onsets,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    beat_position: Option<BeatPosition>,
+    #[serde(skip_serializing_if = "<[f32]>::is_empty")]
+    mfcc: &'a [f32],
+    #[serde(skip_serializing_if = "<[f32]>::is_empty")]
+    chroma: &'a [f32],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<KeyEstimate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chord: Option<Chord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pitch: Option<&'a Pitch>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stereo: Option<StereoRecord<'a>>,
+    spectrum: &'a [f32],
+}
+
+/// Stereo image of a frame, without the goniometer trace.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StereoRecord<'a> {
+    correlation: f32,
+    balance: f32,
+    mid: f32,
+    side: f32,
+    left: &'a [f32],
+    right: &'a [f32],
+}
+
+impl<'a> From<&'a StereoResult> for StereoRecord<'a> {
+    fn from(stereo: &'a StereoResult) -> Self {
+        Self {
+            correlation: stereo.correlation,
+            balance: stereo.balance,
+            mid: stereo.mid,
+            side: stereo.side,
+            left: &stereo.left,
+            right: &stereo.right,
+        }
+    }
+}
+
+struct FrameWriter<W: Write> {
+    out: W,
+    format: Format,
+    /// Set when several files share one stream, so rows say where they're from.
+    file: Option<String>,
+    linear: bool,
+    /// Pitch columns are written; unvoiced frames leave them empty.
+    pitch: bool,
+    /// Stereo image columns are written.
+    stereo: bool,
+    /// The CSV header already written. Files sharing the stream must match it.
+    header: Option<String>,
+    /// The current file's columns have been checked against `header`.
+    checked: bool,
+}
+
+impl<W: Write> FrameWriter<W> {
+    fn new(out: W, options: &Options) -> Self {
+        Self {
+            out,
+            format: options.format,
+            file: None,
+            linear: options.config.spectrum_layout == SpectrumLayout::Linear,
+            pitch: options.config.pitch,
+            stereo: !options.config.mono_downmix,
+            header: None,
+            checked: false,
+        }
+    }
+
+    /// Frames from here on come from `file` (named in each row when set).
+    fn start_file(&mut self, file: Option<String>) {
+        self.file = file;
+        self.checked = false;
+    }
+
+    fn csv_header(&self, frame: &AudioFrame) -> String {
+        use std::fmt::Write as _;
+
+        let mut header = String::new();
+        if self.file.is_some() {
+            header.push_str("file,");
+        }
+        header.push_str(
+            "timestamp,rms,momentary_lufs,short_term_lufs,integrated_lufs,lra,\
+             true_peak_dbtp,centroid,rolloff,flatness,crest,bandwidth,slope,\
+             sub,bass,low_mid,mid,high_mid,presence,brilliance,flux,zcr,beat,bpm,\
+             tempo_confidence,kick,snare,hat,beat_phase,next_beat,bar_position,\
+             bar,beat_in_bar,downbeat,time_signature,phrase_boundary",
+        );
+        for i in 0..frame.mfcc.len() {
+            let _ = write!(header, ",mfcc_{i}");
+        }
+        for i in 0..frame.chroma.len() {
+            let _ = write!(header, ",chroma_{i}");
+        }
+        if !frame.chroma.is_empty() {
+            header.push_str(",key,chord");
+        }
+        if self.pitch {
+            header.push_str(",f0,note,cents,pitch_confidence");
+        }
+        if self.stereo {
+            header.push_str(",correlation,balance,mid_rms,side_rms");
+        }
+        // Linear bins are named by their centre frequency; band ranges are
+        // listed in the summary
+        for i in 0..frame.spectrum.len() {
+            if self.linear {
+                let _ = write!(header, ",{:.1}Hz", i as f32 * frame.bin_hz);
+            } else {
+                let _ = write!(header, ",band_{i}");
+            }
+        }
+        header
+    }
+
+    fn write(&mut self, frame: &AudioFrame) -> std::io::Result<()> {
+        match self.format {
+            Format::JsonLines => {
+                let record = FrameRecord {
+                    file: self.file.as_deref(),
+                    timestamp: frame.timestamp,
+                    rms: frame.rms,
+                    loudness: frame.loudness,
+                    centroid: frame.centroid,
+                    rolloff: frame.rolloff,
+                    flatness: frame.flatness,
+                    crest: frame.crest,
+                    bandwidth: frame.bandwidth,
+                    slope: frame.slope,
+                    band_energy: frame.band_energy,
+                    flux: frame.flux,
+                    zcr: frame.zcr,
+                    beat: frame.beat,
+                    bpm: frame.bpm,
+                    tempo_confidence: frame.tempo_confidence,
+                    onsets: frame.onsets,
+                    beat_position: frame.beat_position,
+                    mfcc: &frame.mfcc,
+                    chroma: &frame.chroma,
+                    key: frame.key,
+                    chord: frame.chord,
+                    pitch: frame.pitch.as_ref(),
+                    stereo: frame.stereo.as_ref().map(StereoRecord::from),
+                    spectrum: &frame.spectrum,
+                };
+                serde_json::to_writer(&mut self.out, &record)?;
+                writeln!(self.out)
+            }
+            Format::Csv => {
+                if !self.checked {
+                    self.checked = true;
+                    let header = self.csv_header(frame);
+                    match &self.header {
+                        None => {
+                            writeln!(self.out, "{header}")?;
+                            self.header = Some(header);
+                        }
+                        Some(written) if *written != header => {
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::InvalidData,
+                                "columns differ from the earlier files (a different sample \
+                                 rate?); use --out-dir to write them separately",
+                            ));
+                        }
+                        Some(_) => {}
+                    }
+                }
+                if let Some(file) = &self.file {
+                    write!(self.out, "\"{}\",", file.replace('"', "\"\""))?;
+                }
+                let e = &frame.band_energy;
+                let l = &frame.loudness;
+                write!(
+                    self.out,
+                    "{:.6},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                    frame.timestamp,
+                    frame.rms,
+                    l.momentary,
+                    l.short_term,
+                    l.integrated,
+                    l.range,
+                    l.true_peak,
+                    frame.centroid,
+                    frame.rolloff,
+                    frame.flatness,
+                    frame.crest,
+                    frame.bandwidth,
+                    frame.slope,
+                    e.sub,
+                    e.bass,
+                    e.low_mid,
+                    e.mid,
+                    e.high_mid,
+                    e.presence,
+                    e.brilliance,
+                    frame.flux,
+                    frame.zcr,
+                    u8::from(frame.beat),
+                    frame.bpm,
+                    frame.tempo_confidence,
+                    frame.onsets.kick.strength,
+                    frame.onsets.snare.strength,
+                    frame.onsets.hat.strength
+                )?;
+                match &frame.beat_position {
+                    Some(p) => write!(
+                        self.out,
+                        ",{},{:.6},{},{},{},{},{},{}",
+                        p.phase,
+                        p.next_beat,
+                        p.bar_position,
+                        p.bar,
+                        p.beat_in_bar,
+                        u8::from(p.downbeat),
+                        p.time_signature,
+                        p.phrase_boundary.map(|b| b.to_string()).unwrap_or_default()
+                    )?,
+                    None => write!(self.out, ",,,,,,,,")?,
+                }
+                for value in frame.mfcc.iter().chain(&frame.chroma) {
+                    write!(self.out, ",{value}")?;
+                }
+                if !frame.chroma.is_empty() {
+                    let name = |v: Option<String>| v.unwrap_or_default();
+                    write!(
+                        self.out,
+                        ",{},{}",
+                        name(frame.key.map(|k| k.to_string())),
+                        name(frame.chord.map(|c| c.to_string()))
+                    )?;
+                }
+                if self.pitch {
+                    match &frame.pitch {
+                        Some(p) => write!(
+                            self.out,
+                            ",{},{},{},{}",
+                            p.frequency, p.note, p.cents, p.confidence
+                        )?,
+                        None => write!(self.out, ",,,,")?,
+                    }
+                }
+                if self.stereo {
+                    match &frame.stereo {
+                        Some(st) => write!(
+                            self.out,
+                            ",{},{},{},{}",
+                            st.correlation, st.balance, st.mid, st.side
+                        )?,
+                        None => write!(self.out, ",,,,")?,
+                    }
+                }
+                for bin in &frame.spectrum {
+                    write!(self.out, ",{bin}")?;
+                }
+                writeln!(self.out)
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FileSummary<'a> {
+    file: &'a str,
+    #[serde(flatten)]
+    analysis: &'a OfflineAnalysis,
+}
+
+/// Analyzes one file. Frames go to their own file under `--out-dir`, or else
+/// to `stdout`, which every file shares so a CSV header is written once.
+fn process_file(
+    input: &Input,
+    options: &Options,
+    stdout: Option<&mut FrameWriter<Box<dyn Write>>>,
+) -> Result<(), String> {
+    let name = input.path.display().to_string();
+    if let Some(dir) = &options.out_dir {
+        let out = output_path(dir, &input.name, "");
+        if let Some(parent) = out.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Cannot create {}: {e}", parent.display()))?;
+        }
+    }
+
+    let mut own;
+    let mut writer = if options.summary_only {
+        None
+    } else if let Some(dir) = &options.out_dir {
+        let suffix = format!(".frames.{}", options.format.extension());
+        let frames_path = output_path(dir, &input.name, &suffix);
+        let file = std::fs::File::create(&frames_path)
+            .map_err(|e| format!("Cannot create {}: {e}", frames_path.display()))?;
+        own = FrameWriter::new(Box::new(BufWriter::new(file)) as Box<dyn Write>, options);
+        own.start_file(None);
+        Some(&mut own)
+    } else if let Some(w) = stdout {
+        w.start_file(Some(name.clone()));
+        Some(w)
+    } else {
+        None
+    };
+
+    let mut write_error = None;
+    let analysis = offline::analyze_file_with(&name, &options.config, |frame| {
+        if let (Some(w), None) = (writer.as_mut(), &write_error) {
+            if let Err(e) = w.write(&frame) {
+                write_error = Some(e);
+            }
+        }
+    })
+    .map_err(|e| e.to_string())?;
+
+    if let Some(e) = write_error {
+        return Err(format!("Failed writing frames: {e}"));
+    }
+    if let Some(w) = writer {
+        w.out
+            .flush()
+            .map_err(|e| format!("Failed writing frames: {e}"))?;
+    }
+
+    let summary = FileSummary {
+        file: &name,
+        analysis: &analysis,
+    };
+    match &options.out_dir {
+        Some(dir) => {
+            let summary_path = output_path(dir, &input.name, ".summary.json");
+            let json = serde_json::to_string_pretty(&summary).map_err(|e| e.to_string())?;
+            std::fs::write(&summary_path, json)
+                .map_err(|e| format!("Cannot write {}: {e}", summary_path.display()))?;
+        }
+        None => {
+            let json = serde_json::to_string(&summary).map_err(|e| e.to_string())?;
+            eprintln!("{json}");
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(o) => o,
+        Err(e) if e.is_empty() => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    if let Some(dir) = &options.out_dir {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            eprintln!("error: cannot create {}: {e}", dir.display());
+            return ExitCode::from(2);
+        }
+    }
+
+    let mut files = collect_files(&options.inputs);
+    if files.is_empty() {
+        eprintln!("error: no audio files found");
+        return ExitCode::from(2);
+    }
+    if options.out_dir.is_some() {
+        if let Err(e) = name_outputs(&mut files) {
+            eprintln!("error: {e}");
+            return ExitCode::from(2);
+        }
+    }
+
+    let mut stdout = (!options.summary_only && options.out_dir.is_none()).then(|| {
+        let out: Box<dyn Write> = Box::new(BufWriter::new(std::io::stdout().lock()));
+        FrameWriter::new(out, &options)
+    });
+
+    let mut failed = 0;
+    for input in &files {
+        if let Err(e) = process_file(input, &options, stdout.as_mut()) {
+            eprintln!("{}: {e}", input.path.display());
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        eprintln!("{failed} of {} files failed", files.len());
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(path: &str, name: &str) -> Input {
+        Input {
+            path: path.into(),
+            name: name.into(),
+        }
+    }
+
+    #[test]
+    fn test_output_names_stay_unique() {
+        let mut files = vec![
+            input("in/a/intro.mp3", "a/intro.mp3"),
+            input("in/b/intro.flac", "b/intro.flac"),
+            input("in/b/outro.mp3", "b/outro.mp3"),
+            input("in/b/outro.flac", "b/outro.flac"),
+        ];
+        name_outputs(&mut files).unwrap();
+        let names: Vec<_> = files.iter().map(|f| f.name.to_str().unwrap()).collect();
+        assert_eq!(names, ["a/intro", "b/intro", "b/outro.mp3", "b/outro.flac"]);
+
+        // The same file name from two file arguments can't be told apart
+        let mut files = vec![
+            input("x/intro.mp3", "intro.mp3"),
+            input("y/intro.mp3", "intro.mp3"),
+        ];
+        assert!(name_outputs(&mut files).is_err());
+    }
+
+    fn frame(timestamp: f64, bin_hz: f32) -> AudioFrame {
+        AudioFrame {
+            timestamp,
+            bin_hz,
+            spectrum: vec![0.0; 4],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_csv_header_written_once_for_shared_stream() {
+        let args = ["-f", "csv", "a.wav"].map(String::from);
+        let options = parse_args(args.into_iter()).unwrap();
+        let mut writer = FrameWriter::new(Vec::new(), &options);
+
+        writer.start_file(Some("a.wav".into()));
+        writer.write(&frame(0.0, 10.0)).unwrap();
+        writer.write(&frame(0.5, 10.0)).unwrap();
+        writer.start_file(Some("b.wav".into()));
+        writer.write(&frame(0.0, 10.0)).unwrap();
+
+        let csv = String::from_utf8(writer.out.clone()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("file,timestamp,"));
+        assert!(lines[0].ends_with(",0.0Hz,10.0Hz,20.0Hz,30.0Hz"));
+        assert!(lines[1].starts_with("\"a.wav\",0.000000,"));
+        assert!(lines[3].starts_with("\"b.wav\",0.000000,"));
+        let columns = lines[0].split(',').count();
+        assert!(lines.iter().all(|l| l.split(',').count() == columns));
+
+        // Linear bins at another sample rate would need different columns
+        writer.start_file(Some("c.wav".into()));
+        assert!(writer.write(&frame(0.0, 11.0)).is_err());
+        assert_eq!(writer.out.len(), csv.len());
+    }
+}
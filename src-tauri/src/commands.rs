@@ -1,17 +1,26 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use ringbuf::traits::Consumer;
-use tauri::{ipc::Channel, State};
+use tauri::{ipc::Channel, AppHandle, Emitter, State};
 
 use crate::audio::{
-    analysis::AudioAnalyzer,
-    beat::BeatDetector,
+    analysis::{AudioAnalyzer, Loudness, LoudnessMeter, StereoAnalyzer},
+    beat::{BeatDetector, TapTempo, MAX_LOCK_BPM, MIN_LOCK_BPM},
     capture,
-    file_player::FilePlayer,
-    ring_buffer::AudioRingBuffer,
-    types::{AudioConfig, AudioDevice, AudioFrame},
+    file_player::{FileInfo, FilePlayer, PlaybackControl, QueuedTrack, TrackQueue},
+    generator::{self, Generator, GeneratorConfig, GENERATOR_SAMPLE_RATE},
+    key::{KeyDetector, KeyEstimate},
+    offline::{self, FeatureSummary, OfflineAnalysis},
+    output,
+    playlist::{Playlist, RepeatMode},
+    ring_buffer::{self, AudioRingBuffer, StereoWindow},
+    types::{
+        AudioConfig, AudioDevice, AudioFrame, PlaybackFinished, PlaybackPosition, TrackChanged,
+        PLAYBACK_FINISHED_EVENT, TRACK_CHANGED_EVENT,
+    },
 };
 use crate::ai::{classifier::Classification, ollama::OllamaClient};
 use crate::config::settings::{self, AppSettings};
@@ -28,7 +37,46 @@ pub struct AudioState {
     pub paused: Arc<AtomicBool>,
     stream: Mutex<Option<StreamWrapper>>,
     analysis_handle: Mutex<Option<std::thread::JoinHandle<()>>>,
-    file_handle: Mutex<Option<std::thread::JoinHandle<()>>>,
+    /// Decode or generator thread feeding the stream, if any.
+    source_handle: Mutex<Option<std::thread::JoinHandle<()>>>,
+    playback: Mutex<Option<Arc<PlaybackControl>>>,
+    /// Messages for the running analysis thread.
+    control: Mutex<Option<Sender<AnalysisControl>>>,
+    analysis: Arc<AnalysisShared>,
+    /// Manual tempo, kept for the session and applied to each new stream.
+    bpm_lock: Mutex<Option<f32>>,
+    taps: Mutex<TapTempo>,
+    /// Clock taps are timed on.
+    epoch: Instant,
+}
+
+/// Messages from commands to the analysis thread, applied between ticks.
+enum AnalysisControl {
+    Config(AudioConfig),
+    /// Locks the tempo, or unlocks with `None`; `beat_at` puts a beat on
+    /// the grid.
+    BpmLock {
+        bpm: Option<f32>,
+        beat_at: Option<Instant>,
+    },
+    /// Shifts the beat grid later by this many seconds.
+    NudgePhase(f64),
+}
+
+/// What the analysis thread publishes for commands and track queues.
+#[derive(Default)]
+struct AnalysisShared {
+    /// Latest key estimate.
+    key: Mutex<Option<KeyEstimate>>,
+    /// Loudness of the current stream, or of the current track when
+    /// playing a queue.
+    loudness: Mutex<Option<LoudnessMeter>>,
+}
+
+impl AnalysisShared {
+    fn loudness(&self) -> Option<Loudness> {
+        self.loudness.lock().ok()?.as_ref().map(|m| m.loudness())
+    }
 }
 
 impl Default for AudioState {
@@ -38,79 +86,366 @@ impl Default for AudioState {
             paused: Arc::new(AtomicBool::new(false)),
             stream: Mutex::new(None),
             analysis_handle: Mutex::new(None),
-            file_handle: Mutex::new(None),
+            source_handle: Mutex::new(None),
+            playback: Mutex::new(None),
+            control: Mutex::new(None),
+            analysis: Arc::default(),
+            bpm_lock: Mutex::new(None),
+            taps: Mutex::default(),
+            epoch: Instant::now(),
+        }
+    }
+}
+
+pub struct PlaylistState(Arc<Mutex<Playlist>>, PlaylistSaver);
+
+impl PlaylistState {
+    /// Restores the saved playlist, starting empty if it can't be read.
+    pub fn load() -> Self {
+        let playlist = settings::load_playlist().unwrap_or_else(|e| {
+            eprintln!("Failed to load playlist: {e}");
+            Playlist::default()
+        });
+        Self(Arc::new(Mutex::new(playlist)), PlaylistSaver::spawn())
+    }
+}
+
+/// Writes playlist snapshots outside the playlist lock. The decode thread
+/// hands its snapshots to a worker so it never waits on the disk; commands
+/// write theirs directly. Snapshots are numbered while the lock is held, and
+/// one older than what's already on disk is dropped, so writers finishing
+/// out of order can't bring back a stale playlist.
+#[derive(Clone)]
+struct PlaylistSaver {
+    revision: Arc<AtomicU64>,
+    /// Revision of the snapshot last written.
+    written: Arc<Mutex<u64>>,
+    worker: Sender<(u64, Playlist)>,
+}
+
+impl PlaylistSaver {
+    fn spawn() -> Self {
+        let (worker, receiver) = mpsc::channel::<(u64, Playlist)>();
+        let saver = Self {
+            revision: Arc::default(),
+            written: Arc::default(),
+            worker,
+        };
+        let writer = saver.clone();
+        std::thread::spawn(move || {
+            while let Ok(snapshot) = receiver.recv() {
+                let latest = receiver.try_iter().last().unwrap_or(snapshot);
+                if let Err(e) = writer.write(latest) {
+                    eprintln!("Failed to save playlist: {e}");
+                }
+            }
+        });
+        saver
+    }
+
+    /// Copies `playlist` for saving; call with the playlist locked.
+    fn snapshot(&self, playlist: &Playlist) -> (u64, Playlist) {
+        let revision = self.revision.fetch_add(1, Ordering::SeqCst) + 1;
+        (revision, playlist.clone())
+    }
+
+    fn write(&self, (revision, playlist): (u64, Playlist)) -> Result<(), AppError> {
+        let mut written = self.written.lock()
+            .map_err(|_| AppError::Audio("Failed to lock playlist saver".into()))?;
+        if revision > *written {
+            settings::save_playlist(&playlist)?;
+            *written = revision;
+        }
+        Ok(())
+    }
+
+    /// Queues a snapshot for the worker.
+    fn write_later(&self, snapshot: (u64, Playlist)) {
+        let _ = self.worker.send(snapshot);
+    }
+}
+
+/// Track id used for one-off files played outside the playlist.
+const SINGLE_FILE_TRACK_ID: u64 = u64::MAX;
+
+fn emit_finished(
+    app: &AppHandle,
+    analysis: &AnalysisShared,
+    track: &QueuedTrack,
+    position_secs: f64,
+) {
+    let payload = PlaybackFinished {
+        path: track.path.clone(),
+        duration_secs: position_secs,
+        loudness: analysis.loudness(),
+    };
+    if let Err(e) = app.emit(PLAYBACK_FINISHED_EVENT, payload) {
+        eprintln!("Failed to emit playback finished event: {e}");
+    }
+}
+
+/// Queue for a single dropped file: plays it once and reports the end.
+struct SingleFileQueue {
+    app: AppHandle,
+    analysis: Arc<AnalysisShared>,
+}
+
+impl TrackQueue for SingleFileQueue {
+    fn next_after(&mut self, _current: &QueuedTrack) -> Option<QueuedTrack> {
+        None
+    }
+
+    fn started(&mut self, _track: &QueuedTrack, _duration_secs: f64) {}
+
+    fn finished(&mut self, track: &QueuedTrack, position_secs: f64) {
+        emit_finished(&self.app, &self.analysis, track, position_secs);
+    }
+}
+
+/// Queue backed by the persisted playlist; follows its order, shuffle and
+/// repeat settings as they are at each track boundary.
+struct PlaylistQueue {
+    playlist: Arc<Mutex<Playlist>>,
+    saver: PlaylistSaver,
+    app: AppHandle,
+    analysis: Arc<AnalysisShared>,
+}
+
+impl TrackQueue for PlaylistQueue {
+    fn next_after(&mut self, current: &QueuedTrack) -> Option<QueuedTrack> {
+        let playlist = self.playlist.lock().ok()?;
+        playlist.next_after(current.id).map(|t| t.queued())
+    }
+
+    fn started(&mut self, track: &QueuedTrack, duration_secs: f64) {
+        // Meter each track on its own
+        if let Ok(mut meter) = self.analysis.loudness.lock() {
+            if let Some(meter) = meter.as_mut() {
+                meter.reset();
+            }
+        }
+
+        // Update under the lock, save outside it
+        let (index, snapshot) = match self.playlist.lock() {
+            Ok(mut playlist) => {
+                playlist.set_current(track.id);
+                (playlist.index_of(track.id), Some(self.saver.snapshot(&playlist)))
+            }
+            Err(_) => (None, None),
+        };
+        if let Some(snapshot) = snapshot {
+            self.saver.write_later(snapshot);
+        }
+
+        let payload = TrackChanged {
+            id: track.id,
+            index,
+            path: track.path.clone(),
+            duration_secs,
+        };
+        if let Err(e) = self.app.emit(TRACK_CHANGED_EVENT, payload) {
+            eprintln!("Failed to emit track changed event: {e}");
+        }
+    }
+
+    fn finished(&mut self, track: &QueuedTrack, position_secs: f64) {
+        emit_finished(&self.app, &self.analysis, track, position_secs);
+    }
+}
+
+/// Rate analysis frames come at: `target_fps`, unless the hop holds them
+/// back.
+fn analysis_frame_rate(config: &AudioConfig, sample_rate: u32) -> f32 {
+    match config.hop_size {
+        Some(hop) => (sample_rate as f32 / hop as f32).min(config.target_fps as f32),
+        None => config.target_fps as f32,
+    }
+}
+
+fn frame_interval(config: &AudioConfig) -> Duration {
+    Duration::from_micros(1_000_000 / config.target_fps as u64)
+}
+
+/// The parts of the analysis thread built from an `AudioConfig`.
+struct LivePipeline {
+    fft_size: usize,
+    min_hop: usize,
+    frame_interval: Duration,
+    analyzer: AudioAnalyzer,
+    /// `None` when analyzing the mono downmix only.
+    stereo: Option<StereoAnalyzer>,
+    window: StereoWindow,
+}
+
+impl LivePipeline {
+    fn new(config: &AudioConfig, sample_rate: u32) -> Self {
+        let mut pipeline = Self {
+            fft_size: config.fft_size,
+            min_hop: 1,
+            frame_interval: frame_interval(config),
+            analyzer: AudioAnalyzer::new(config.fft_size, sample_rate),
+            stereo: None,
+            window: StereoWindow::new(config.fft_size),
+        };
+        pipeline.update(config, sample_rate);
+        pipeline
+    }
+
+    /// Applies `config` to the running pipeline. The FFT plan and the sample
+    /// window are only rebuilt when the FFT size changes; the window then
+    /// refills before the next frame.
+    fn update(&mut self, config: &AudioConfig, sample_rate: u32) {
+        if config.fft_size != self.fft_size {
+            self.fft_size = config.fft_size;
+            self.analyzer = AudioAnalyzer::new(config.fft_size, sample_rate);
+            self.window = StereoWindow::new(config.fft_size);
+            self.stereo = None;
+        }
+        self.min_hop = config.hop_size.unwrap_or(1);
+        self.frame_interval = frame_interval(config);
+
+        let frame_rate = analysis_frame_rate(config, sample_rate);
+        self.analyzer.configure(config, frame_rate);
+        if config.mono_downmix {
+            self.stereo = None;
+        } else {
+            self.stereo
+                .get_or_insert_with(|| StereoAnalyzer::new(config.fft_size, sample_rate))
+                .configure(config, frame_rate);
         }
     }
 }
 
 /// Shared analysis thread logic used by both mic capture and file playback.
+///
+/// Each tick drains everything the producer wrote into a sliding window and
+/// analyzes the latest `fft_size` frames, so consecutive frames overlap
+/// rather than waiting for a whole new block. The producer writes
+/// interleaved left/right pairs. Messages on `control` are applied between
+/// ticks without touching the stream.
 fn spawn_analysis_thread(
     mut consumer: impl Consumer<Item = f32> + Send + 'static,
     channel: Channel<AudioFrame>,
-    fft_size: usize,
-    target_fps: u32,
-    sensitivity: f32,
+    config: &AudioConfig,
+    sample_rate: u32,
     running: Arc<AtomicBool>,
+    shared: Arc<AnalysisShared>,
+    control: Receiver<AnalysisControl>,
 ) -> std::thread::JoinHandle<()> {
-    let frame_interval = std::time::Duration::from_micros(1_000_000 / target_fps as u64);
+    let config = config.clone();
+    // The ring was sized for the starting rate, so keep draining at least
+    // that often even if frames are slowed down later
+    let drain_interval = frame_interval(&config);
 
     std::thread::spawn(move || {
-        let mut analyzer = AudioAnalyzer::new(fft_size);
-        let mut beat_detector = BeatDetector::new(sensitivity);
-        let mut sample_buffer = vec![0.0f32; fft_size];
+        let mut pipeline = LivePipeline::new(&config, sample_rate);
+        let mut beat_detector = BeatDetector::new(config.sensitivity);
+        beat_detector.set_onset_bands(&config.onset_bands, pipeline.analyzer.bin_hz());
+        let mut key_detector = KeyDetector::new();
+        if let Ok(mut k) = shared.key.lock() {
+            *k = None;
+        }
+        if let Ok(mut meter) = shared.loudness.lock() {
+            *meter = Some(LoudnessMeter::new(sample_rate));
+        }
         let mut stall_count: u32 = 0;
         let start = Instant::now();
+        let mut last_frame: Option<Instant> = None;
 
         while running.load(Ordering::Relaxed) {
             let frame_start = Instant::now();
 
-            let available = consumer.pop_slice(&mut sample_buffer);
-
-            if available >= fft_size {
-                stall_count = 0;
-                if let Some(result) = analyzer.analyze(&sample_buffer) {
-                    let timestamp = start.elapsed().as_secs_f64();
-                    let (beat, bpm) = beat_detector.detect(&result.spectrum, timestamp);
-
-                    let frame = AudioFrame {
-                        spectrum: result.spectrum,
-                        waveform: result.waveform,
-                        rms: result.rms,
-                        centroid: result.centroid,
-                        flux: result.flux,
-                        zcr: result.zcr,
-                        beat,
-                        bpm,
-                        timestamp,
-                    };
-
-                    if channel.send(frame).is_err() {
-                        break;
+            let mut new_config = None;
+            for message in control.try_iter() {
+                match message {
+                    AnalysisControl::Config(config) => new_config = Some(config),
+                    AnalysisControl::BpmLock { bpm, beat_at } => {
+                        beat_detector.set_bpm_lock(bpm);
+                        if let Some(at) = beat_at {
+                            let timestamp = at.saturating_duration_since(start).as_secs_f64();
+                            beat_detector.align_beat(timestamp);
+                        }
                     }
+                    AnalysisControl::NudgePhase(secs) => beat_detector.nudge_phase(secs),
+                }
+            }
+            if let Some(config) = new_config {
+                pipeline.update(&config, sample_rate);
+                beat_detector.set_sensitivity(config.sensitivity);
+                beat_detector.set_onset_bands(&config.onset_bands, pipeline.analyzer.bin_hz());
+            }
+            let LivePipeline {
+                min_hop,
+                frame_interval,
+                analyzer,
+                stereo,
+                window,
+                ..
+            } = &mut pipeline;
+
+            // The meter needs every sample once, so it takes each chunk as
+            // it's drained rather than what's left in the window. It always
+            // meters the pair: `mono_downmix` is a display option only
+            let mut meter = shared.loudness.lock().ok();
+            let read = window.fill_from(&mut consumer, |left, right, _| {
+                if let Some(meter) = meter.as_mut().and_then(|m| m.as_mut()) {
+                    meter.process_stereo(left, right);
                 }
+            });
+            drop(meter);
+
+            if read > 0 {
+                stall_count = 0;
             } else {
                 stall_count += 1;
                 // 180 stalls at 60fps ≈ 3 seconds of no data → device likely disconnected
                 if stall_count >= 180 {
                     let sentinel = AudioFrame {
-                        spectrum: vec![],
-                        waveform: vec![],
                         rms: -1.0,
-                        centroid: 0.0,
-                        flux: 0.0,
-                        zcr: 0.0,
-                        beat: false,
-                        bpm: 0.0,
                         timestamp: start.elapsed().as_secs_f64(),
+                        ..Default::default()
                     };
                     let _ = channel.send(sentinel);
                     break;
                 }
             }
 
+            // Half a tick of slack so frames keep to the rate despite jitter
+            let due = last_frame
+                .is_none_or(|t| t.elapsed() + drain_interval / 2 >= *frame_interval);
+            if due && window.is_full() && window.fresh() >= *min_hop {
+                window.take_fresh();
+                last_frame = Some(frame_start);
+                if let Some(result) = analyzer.analyze(window.mid()) {
+                    let timestamp = start.elapsed().as_secs_f64();
+                    let (beat, bpm) = beat_detector.detect(&result.spectrum, timestamp);
+
+                    let mut frame = AudioFrame::from_analysis(result, beat, bpm, timestamp);
+                    frame.tempo_confidence = beat_detector.tempo_confidence();
+                    frame.bpm_locked = beat_detector.bpm_lock().is_some();
+                    frame.onsets = beat_detector.onsets();
+                    frame.beat_position = beat_detector.position();
+                    frame.key = key_detector.update(&result.chroma, timestamp);
+                    if let Ok(mut k) = shared.key.lock() {
+                        *k = frame.key;
+                    }
+                    if let Some(loudness) = shared.loudness() {
+                        frame.loudness = loudness;
+                    }
+                    if let Some(stereo) = stereo.as_mut() {
+                        frame.stereo = stereo.analyze(window.left(), window.right()).cloned();
+                    }
+
+                    if channel.send(frame).is_err() {
+                        break;
+                    }
+                }
+            }
+
             let elapsed = frame_start.elapsed();
-            if elapsed < frame_interval {
-                std::thread::sleep(frame_interval - elapsed);
+            let tick = (*frame_interval).min(drain_interval);
+            if elapsed < tick {
+                std::thread::sleep(tick - elapsed);
             }
         }
     })
@@ -126,6 +461,11 @@ pub fn list_audio_devices() -> Result<Vec<AudioDevice>, AppError> {
     capture::list_devices()
 }
 
+#[tauri::command]
+pub fn list_output_devices() -> Result<Vec<AudioDevice>, AppError> {
+    output::list_output_devices()
+}
+
 #[tauri::command]
 pub fn start_audio(
     config: AudioConfig,
@@ -133,15 +473,14 @@ pub fn start_audio(
     state: State<'_, AudioState>,
 ) -> Result<(), AppError> {
     let config = config.validated();
-    let fft_size = config.fft_size;
-    let target_fps = config.target_fps;
-    let sensitivity = config.sensitivity;
 
     // Stop any existing capture
     stop_existing(&state)?;
 
-    // Ring buffer: 4x FFT size for headroom
-    let ring = AudioRingBuffer::new(fft_size * 4);
+    let ring = AudioRingBuffer::new(ring_buffer::analysis_capacity(
+        config.fft_size,
+        config.target_fps,
+    ));
     let (producer, consumer) = ring.split();
 
     let running = state.running.clone();
@@ -149,7 +488,7 @@ pub fn start_audio(
     state.paused.store(false, Ordering::SeqCst);
 
     // Start audio capture
-    let (stream, _sample_rate) = capture::start_capture(&config, producer, running.clone())?;
+    let (stream, sample_rate) = capture::start_capture(&config, producer, running.clone())?;
 
     {
         let mut s = state.stream.lock()
@@ -157,7 +496,15 @@ pub fn start_audio(
         *s = Some(StreamWrapper(stream));
     }
 
-    let handle = spawn_analysis_thread(consumer, channel, fft_size, target_fps, sensitivity, running);
+    let handle = spawn_analysis_thread(
+        consumer,
+        channel,
+        &config,
+        sample_rate,
+        running,
+        state.analysis.clone(),
+        control_channel(&state)?,
+    );
 
     {
         let mut h = state.analysis_handle.lock()
@@ -168,23 +515,77 @@ pub fn start_audio(
     Ok(())
 }
 
+/// Analyzes a synthetic test signal instead of a device, for demos and
+/// calibrating visuals without a mic. Nothing is played out.
 #[tauri::command]
-pub fn start_file_audio(
-    path: String,
+pub fn start_generator_audio(
+    generator: GeneratorConfig,
     config: AudioConfig,
     channel: Channel<AudioFrame>,
     state: State<'_, AudioState>,
-) -> Result<f64, AppError> {
+) -> Result<(), AppError> {
     let config = config.validated();
-    let fft_size = config.fft_size;
-    let target_fps = config.target_fps;
-    let sensitivity = config.sensitivity;
 
     // Stop any existing capture
     stop_existing(&state)?;
 
-    // Ring buffer: 4x FFT size for headroom
-    let ring = AudioRingBuffer::new(fft_size * 4);
+    let ring = AudioRingBuffer::new(ring_buffer::analysis_capacity(
+        config.fft_size,
+        config.target_fps,
+    ));
+    let (producer, consumer) = ring.split();
+
+    let running = state.running.clone();
+    running.store(true, Ordering::SeqCst);
+    let paused = state.paused.clone();
+    paused.store(false, Ordering::SeqCst);
+
+    let source = Generator::new(&generator.validated(), GENERATOR_SAMPLE_RATE);
+    let source_handle = generator::start_generator(source, producer, running.clone(), paused);
+
+    {
+        let mut fh = state.source_handle.lock()
+            .map_err(|_| AppError::Audio("Failed to lock source handle".into()))?;
+        *fh = Some(source_handle);
+    }
+
+    let handle = spawn_analysis_thread(
+        consumer,
+        channel,
+        &config,
+        GENERATOR_SAMPLE_RATE,
+        running,
+        state.analysis.clone(),
+        control_channel(&state)?,
+    );
+
+    {
+        let mut h = state.analysis_handle.lock()
+            .map_err(|_| AppError::Audio("Failed to lock analysis handle".into()))?;
+        *h = Some(handle);
+    }
+
+    Ok(())
+}
+
+/// Starts file playback of `first` followed by `queue`, with analysis fed
+/// from the output stream.
+fn start_queue(
+    first: QueuedTrack,
+    queue: impl TrackQueue,
+    config: AudioConfig,
+    channel: Channel<AudioFrame>,
+    state: &State<'_, AudioState>,
+) -> Result<FileInfo, AppError> {
+    let config = config.validated();
+
+    // Stop any existing capture
+    stop_existing(state)?;
+
+    let ring = AudioRingBuffer::new(ring_buffer::analysis_capacity(
+        config.fft_size,
+        config.target_fps,
+    ));
     let (producer, consumer) = ring.split();
 
     let running = state.running.clone();
@@ -192,18 +593,42 @@ pub fn start_file_audio(
     let paused = state.paused.clone();
     paused.store(false, Ordering::SeqCst);
 
-    // Start file decode + paced playback
-    let (file_handle, file_info) =
-        FilePlayer::play_file(&path, producer, running.clone(), paused, target_fps)?;
+    // Start file decode + output playback (output stream feeds analysis)
+    let (stream, file_handle, file_info, control) = FilePlayer::play_queue(
+        first,
+        queue,
+        config.output_device_name.as_deref(),
+        producer,
+        running.clone(),
+        paused,
+    )?;
+
+    {
+        let mut s = state.stream.lock()
+            .map_err(|_| AppError::Audio("Failed to lock stream state".into()))?;
+        *s = Some(StreamWrapper(stream));
+    }
+
+    {
+        let mut p = state.playback.lock()
+            .map_err(|_| AppError::Audio("Failed to lock playback state".into()))?;
+        *p = Some(control);
+    }
 
     {
-        let mut fh = state.file_handle.lock()
-            .map_err(|_| AppError::Audio("Failed to lock file handle".into()))?;
+        let mut fh = state.source_handle.lock()
+            .map_err(|_| AppError::Audio("Failed to lock source handle".into()))?;
         *fh = Some(file_handle);
     }
 
     let analysis_handle = spawn_analysis_thread(
-        consumer, channel, fft_size, target_fps, sensitivity, running,
+        consumer,
+        channel,
+        &config,
+        file_info.sample_rate,
+        running,
+        state.analysis.clone(),
+        control_channel(state)?,
     );
 
     {
@@ -212,6 +637,26 @@ pub fn start_file_audio(
         *h = Some(analysis_handle);
     }
 
+    Ok(file_info)
+}
+
+#[tauri::command]
+pub fn start_file_audio(
+    path: String,
+    config: AudioConfig,
+    channel: Channel<AudioFrame>,
+    app: AppHandle,
+    state: State<'_, AudioState>,
+) -> Result<f64, AppError> {
+    let track = QueuedTrack {
+        id: SINGLE_FILE_TRACK_ID,
+        path,
+    };
+    let queue = SingleFileQueue {
+        app,
+        analysis: state.analysis.clone(),
+    };
+    let file_info = start_queue(track, queue, config, channel, &state)?;
     Ok(file_info.duration_secs)
 }
 
@@ -222,6 +667,278 @@ pub fn toggle_pause(state: State<'_, AudioState>) -> Result<bool, AppError> {
     Ok(!was_paused)
 }
 
+fn current_playback(state: &State<'_, AudioState>) -> Result<Arc<PlaybackControl>, AppError> {
+    let p = state.playback.lock()
+        .map_err(|_| AppError::Audio("Failed to lock playback state".into()))?;
+    p.clone()
+        .ok_or_else(|| AppError::Audio("No file is playing".into()))
+}
+
+#[tauri::command]
+pub fn seek_file(position_secs: f64, state: State<'_, AudioState>) -> Result<(), AppError> {
+    current_playback(&state)?.request_seek(position_secs)
+}
+
+/// Key of the live stream so far; `None` until enough tonal audio has
+/// been heard, or when chroma is disabled.
+#[tauri::command]
+pub fn get_key_estimate(state: State<'_, AudioState>) -> Result<Option<KeyEstimate>, AppError> {
+    let key = state.analysis.key.lock()
+        .map_err(|_| AppError::Audio("Failed to lock key estimate".into()))?;
+    Ok(*key)
+}
+
+#[tauri::command]
+pub fn get_playback_position(state: State<'_, AudioState>) -> Result<PlaybackPosition, AppError> {
+    let control = current_playback(&state)?;
+    let (path, position_secs, duration_secs) = control
+        .position()
+        .ok_or_else(|| AppError::Audio("No file is playing".into()))?;
+    Ok(PlaybackPosition {
+        path,
+        position_secs,
+        duration_secs,
+        finished: control.is_finished(),
+    })
+}
+
+/// Locks the playlist, applies `f`, saves it and returns the new state.
+fn update_playlist(
+    playlist: &State<'_, PlaylistState>,
+    f: impl FnOnce(&mut Playlist),
+) -> Result<Playlist, AppError> {
+    let snapshot = {
+        let mut p = playlist.0.lock()
+            .map_err(|_| AppError::Audio("Failed to lock playlist".into()))?;
+        f(&mut p);
+        playlist.1.snapshot(&p)
+    };
+    let updated = snapshot.1.clone();
+    playlist.1.write(snapshot)?;
+    Ok(updated)
+}
+
+#[tauri::command]
+pub fn playlist_get(playlist: State<'_, PlaylistState>) -> Result<Playlist, AppError> {
+    let p = playlist.0.lock()
+        .map_err(|_| AppError::Audio("Failed to lock playlist".into()))?;
+    Ok(p.clone())
+}
+
+#[tauri::command]
+pub fn playlist_add(
+    paths: Vec<String>,
+    playlist: State<'_, PlaylistState>,
+) -> Result<Playlist, AppError> {
+    update_playlist(&playlist, |p| p.add(paths))
+}
+
+#[tauri::command]
+pub fn playlist_remove(
+    index: usize,
+    playlist: State<'_, PlaylistState>,
+) -> Result<Playlist, AppError> {
+    update_playlist(&playlist, |p| {
+        p.remove(index);
+    })
+}
+
+#[tauri::command]
+pub fn playlist_move(
+    from: usize,
+    to: usize,
+    playlist: State<'_, PlaylistState>,
+) -> Result<Playlist, AppError> {
+    update_playlist(&playlist, |p| {
+        p.move_track(from, to);
+    })
+}
+
+#[tauri::command]
+pub fn playlist_clear(playlist: State<'_, PlaylistState>) -> Result<Playlist, AppError> {
+    update_playlist(&playlist, |p| p.clear())
+}
+
+#[tauri::command]
+pub fn playlist_set_shuffle(
+    shuffle: bool,
+    playlist: State<'_, PlaylistState>,
+) -> Result<Playlist, AppError> {
+    update_playlist(&playlist, |p| p.set_shuffle(shuffle))
+}
+
+#[tauri::command]
+pub fn playlist_set_repeat(
+    repeat: RepeatMode,
+    playlist: State<'_, PlaylistState>,
+) -> Result<Playlist, AppError> {
+    update_playlist(&playlist, |p| p.set_repeat(repeat))
+}
+
+/// Starts the playlist at `index`, or at the current/first track.
+#[tauri::command]
+pub fn playlist_play(
+    index: Option<usize>,
+    config: AudioConfig,
+    channel: Channel<AudioFrame>,
+    app: AppHandle,
+    state: State<'_, AudioState>,
+    playlist: State<'_, PlaylistState>,
+) -> Result<f64, AppError> {
+    let first = {
+        let p = playlist.0.lock()
+            .map_err(|_| AppError::Audio("Failed to lock playlist".into()))?;
+        let track = match index {
+            Some(i) => p.tracks.get(i),
+            None => p
+                .current_id
+                .and_then(|id| p.track(id))
+                .or_else(|| p.tracks.first()),
+        };
+        track
+            .map(|t| t.queued())
+            .ok_or_else(|| AppError::Audio("Playlist has no track to play".into()))?
+    };
+
+    let queue = PlaylistQueue {
+        playlist: playlist.0.clone(),
+        saver: playlist.1.clone(),
+        app,
+        analysis: state.analysis.clone(),
+    };
+    let file_info = start_queue(first, queue, config, channel, &state)?;
+    Ok(file_info.duration_secs)
+}
+
+fn playlist_skip(
+    state: &State<'_, AudioState>,
+    playlist: &State<'_, PlaylistState>,
+    forward: bool,
+) -> Result<(), AppError> {
+    let control = current_playback(state)?;
+    let current = control
+        .audible_track()
+        .ok_or_else(|| AppError::Audio("No file is playing".into()))?;
+
+    let target = {
+        let p = playlist.0.lock()
+            .map_err(|_| AppError::Audio("Failed to lock playlist".into()))?;
+        let track = if forward {
+            p.skip_forward(current.id)
+        } else {
+            p.skip_back(current.id)
+        };
+        track
+            .map(|t| t.queued())
+            .ok_or_else(|| AppError::Audio("No track to skip to".into()))?
+    };
+
+    control.request_jump(target)
+}
+
+#[tauri::command]
+pub fn playlist_next(
+    state: State<'_, AudioState>,
+    playlist: State<'_, PlaylistState>,
+) -> Result<(), AppError> {
+    playlist_skip(&state, &playlist, true)
+}
+
+#[tauri::command]
+pub fn playlist_previous(
+    state: State<'_, AudioState>,
+    playlist: State<'_, PlaylistState>,
+) -> Result<(), AppError> {
+    playlist_skip(&state, &playlist, false)
+}
+
+/// Opens the control channel to a new analysis thread, replacing the last
+/// one's, and passes on the session's tempo lock.
+fn control_channel(state: &State<'_, AudioState>) -> Result<Receiver<AnalysisControl>, AppError> {
+    let (sender, receiver) = mpsc::channel();
+    let bpm = *state.bpm_lock.lock()
+        .map_err(|_| AppError::Audio("Failed to lock BPM lock".into()))?;
+    if bpm.is_some() {
+        let _ = sender.send(AnalysisControl::BpmLock { bpm, beat_at: None });
+    }
+    let mut control = state.control.lock()
+        .map_err(|_| AppError::Audio("Failed to lock analysis control".into()))?;
+    *control = Some(sender);
+    Ok(receiver)
+}
+
+/// Sends `message` to the analysis thread; `false` if nothing is running.
+fn send_control(state: &State<'_, AudioState>, message: AnalysisControl) -> Result<bool, AppError> {
+    let control = state.control.lock()
+        .map_err(|_| AppError::Audio("Failed to lock analysis control".into()))?;
+    Ok(control
+        .as_ref()
+        .is_some_and(|sender| sender.send(message).is_ok()))
+}
+
+/// Applies new analysis settings (sensitivity, FPS, hop, smoothing, bands,
+/// scaling and features) to the running stream without restarting it.
+/// Device changes still need `start_audio`.
+#[tauri::command]
+pub fn update_audio_config(config: AudioConfig, state: State<'_, AudioState>) -> Result<(), AppError> {
+    if send_control(&state, AnalysisControl::Config(config.validated()))? {
+        Ok(())
+    } else {
+        Err(AppError::Audio("Audio is not running".into()))
+    }
+}
+
+/// Locks the tempo to `bpm` for the rest of the session, or goes back to
+/// detection with `None`. Beats follow the lock's clock grid.
+#[tauri::command]
+pub fn set_bpm_lock(bpm: Option<f32>, state: State<'_, AudioState>) -> Result<(), AppError> {
+    if bpm.is_some_and(|b| !(MIN_LOCK_BPM..=MAX_LOCK_BPM).contains(&b)) {
+        return Err(AppError::Audio(format!(
+            "BPM lock must be between {MIN_LOCK_BPM} and {MAX_LOCK_BPM}"
+        )));
+    }
+    {
+        let mut lock = state.bpm_lock.lock()
+            .map_err(|_| AppError::Audio("Failed to lock BPM lock".into()))?;
+        *lock = bpm;
+    }
+    send_control(&state, AnalysisControl::BpmLock { bpm, beat_at: None })?;
+    Ok(())
+}
+
+/// Records a tap. From the second tap on, locks the tempo to the tapped one
+/// and returns it; every tap while locked puts a beat on the grid.
+#[tauri::command]
+pub fn tap_tempo(state: State<'_, AudioState>) -> Result<Option<f32>, AppError> {
+    let now = Instant::now();
+    let tapped = state.taps.lock()
+        .map_err(|_| AppError::Audio("Failed to lock tap tempo".into()))?
+        .tap(now.duration_since(state.epoch).as_secs_f64());
+
+    let bpm = {
+        let mut lock = state.bpm_lock.lock()
+            .map_err(|_| AppError::Audio("Failed to lock BPM lock".into()))?;
+        if tapped.is_some() {
+            *lock = tapped;
+        }
+        *lock
+    };
+    if bpm.is_some() {
+        send_control(&state, AnalysisControl::BpmLock { bpm, beat_at: Some(now) })?;
+    }
+    Ok(tapped)
+}
+
+/// Shifts the beat grid later by `ms`, or earlier if negative.
+#[tauri::command]
+pub fn nudge_beat_phase(ms: f64, state: State<'_, AudioState>) -> Result<(), AppError> {
+    if send_control(&state, AnalysisControl::NudgePhase(ms / 1000.0))? {
+        Ok(())
+    } else {
+        Err(AppError::Audio("Audio is not running".into()))
+    }
+}
+
 fn stop_existing(state: &State<'_, AudioState>) -> Result<(), AppError> {
     state.running.store(false, Ordering::SeqCst);
 
@@ -232,10 +949,22 @@ fn stop_existing(state: &State<'_, AudioState>) -> Result<(), AppError> {
         *s = None;
     }
 
-    // Join file thread if any
     {
-        let mut fh = state.file_handle.lock()
-            .map_err(|_| AppError::Audio("Failed to lock file handle".into()))?;
+        let mut p = state.playback.lock()
+            .map_err(|_| AppError::Audio("Failed to lock playback state".into()))?;
+        *p = None;
+    }
+
+    {
+        let mut c = state.control.lock()
+            .map_err(|_| AppError::Audio("Failed to lock analysis control".into()))?;
+        *c = None;
+    }
+
+    // Join file or generator thread if any
+    {
+        let mut fh = state.source_handle.lock()
+            .map_err(|_| AppError::Audio("Failed to lock source handle".into()))?;
         if let Some(handle) = fh.take() {
             let _ = handle.join();
         }
@@ -260,6 +989,22 @@ pub fn stop_audio(state: State<'_, AudioState>) -> Result<(), AppError> {
     Ok(())
 }
 
+/// Analyzes a whole file faster than realtime, off the UI thread. Set
+/// `include_frames` to get every frame back as well as the summaries.
+#[tauri::command]
+pub async fn analyze_file(
+    path: String,
+    config: AudioConfig,
+    include_frames: Option<bool>,
+) -> Result<OfflineAnalysis, AppError> {
+    let config = config.validated();
+    tokio::task::spawn_blocking(move || {
+        offline::analyze_file(&path, &config, include_frames.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| AppError::Audio(format!("Analysis task failed: {e}")))?
+}
+
 #[tauri::command]
 pub async fn check_ollama() -> Result<bool, AppError> {
     let client = OllamaClient::new();
@@ -267,18 +1012,9 @@ pub async fn check_ollama() -> Result<bool, AppError> {
 }
 
 #[tauri::command]
-pub async fn classify_audio(
-    avg_rms: f32,
-    avg_centroid: f32,
-    avg_flux: f32,
-    avg_zcr: f32,
-    bpm: f32,
-    beat_regularity: f32,
-) -> Result<Classification, AppError> {
+pub async fn classify_audio(summary: FeatureSummary) -> Result<Classification, AppError> {
     let classifier = crate::ai::classifier::AudioClassifier::new();
-    classifier
-        .classify(avg_rms, avg_centroid, avg_flux, avg_zcr, bpm, beat_regularity)
-        .await
+    classifier.classify(&summary).await
 }
 
 #[tauri::command]
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+use crate::audio::playlist::Playlist;
 use crate::error::AppError;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,13 +31,21 @@ impl Default for AppSettings {
     }
 }
 
-fn config_path() -> Result<PathBuf, AppError> {
+fn config_dir() -> Result<PathBuf, AppError> {
     let home = dirs::home_dir().ok_or_else(|| AppError::Config("No home directory".into()))?;
     let dir = home.join(".synthwave");
     if !dir.exists() {
         std::fs::create_dir_all(&dir)?;
     }
-    Ok(dir.join("config.json"))
+    Ok(dir)
+}
+
+fn config_path() -> Result<PathBuf, AppError> {
+    Ok(config_dir()?.join("config.json"))
+}
+
+fn playlist_path() -> Result<PathBuf, AppError> {
+    Ok(config_dir()?.join("playlist.json"))
 }
 
 pub fn load_settings() -> Result<AppSettings, AppError> {
@@ -56,6 +65,23 @@ pub fn save_settings(settings: &AppSettings) -> Result<(), AppError> {
     Ok(())
 }
 
+pub fn load_playlist() -> Result<Playlist, AppError> {
+    let path = playlist_path()?;
+    if !path.exists() {
+        return Ok(Playlist::default());
+    }
+    let data = std::fs::read_to_string(&path)?;
+    let playlist: Playlist = serde_json::from_str(&data)?;
+    Ok(playlist)
+}
+
+pub fn save_playlist(playlist: &Playlist) -> Result<(), AppError> {
+    let path = playlist_path()?;
+    let data = serde_json::to_string_pretty(playlist)?;
+    std::fs::write(&path, data)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,23 +1,49 @@
+#[cfg(feature = "gui")]
 mod ai;
-mod audio;
+pub mod audio;
+#[cfg(feature = "gui")]
 mod commands;
+#[cfg(feature = "gui")]
 mod config;
-mod error;
+pub mod error;
 
-use commands::AudioState;
+#[cfg(feature = "gui")]
+use commands::{AudioState, PlaylistState};
 
+#[cfg(feature = "gui")]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .manage(AudioState::default())
+        .manage(PlaylistState::load())
         .invoke_handler(tauri::generate_handler![
             commands::get_app_info,
             commands::list_audio_devices,
+            commands::list_output_devices,
             commands::start_audio,
             commands::stop_audio,
+            commands::update_audio_config,
+            commands::set_bpm_lock,
+            commands::tap_tempo,
+            commands::nudge_beat_phase,
             commands::start_file_audio,
+            commands::start_generator_audio,
             commands::toggle_pause,
+            commands::seek_file,
+            commands::get_playback_position,
+            commands::get_key_estimate,
+            commands::playlist_get,
+            commands::playlist_add,
+            commands::playlist_remove,
+            commands::playlist_move,
+            commands::playlist_clear,
+            commands::playlist_set_shuffle,
+            commands::playlist_set_repeat,
+            commands::playlist_play,
+            commands::playlist_next,
+            commands::playlist_previous,
+            commands::analyze_file,
             commands::check_ollama,
             commands::classify_audio,
             commands::load_settings,
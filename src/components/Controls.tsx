@@ -1,4 +1,4 @@
-import { useEffect, useRef, useState } from "react";
+import { useCallback, useEffect, useRef, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { useAudioStream } from "../hooks/useAudioStream";
 import { useRecorder } from "../hooks/useRecorder";
@@ -8,9 +8,30 @@ import { useSettingsStore } from "../stores/settingsStore";
 import { useToastStore } from "../stores/toastStore";
 import { getCanvasRef } from "../stores/canvasRefStore";
 import { THEMES } from "../themes";
+import type { Signal } from "../types/audio";
+
+/** Built-in test signals, offered alongside the input devices. */
+const TEST_SIGNALS: { id: string; label: string; signal: Signal }[] = [
+  { id: "sine", label: "Sine 440 Hz", signal: { kind: "sine", frequencyHz: 440 } },
+  {
+    id: "sweep",
+    label: "Sweep 20 Hz - 20 kHz",
+    signal: { kind: "sweep", startHz: 20, endHz: 20000, durationSecs: 10 },
+  },
+  { id: "pink", label: "Pink noise", signal: { kind: "pinkNoise" } },
+  { id: "white", label: "White noise", signal: { kind: "whiteNoise" } },
+  { id: "clicks", label: "Click track 120 BPM", signal: { kind: "clicks", bpm: 120 } },
+  {
+    id: "chord",
+    label: "A minor chord",
+    signal: { kind: "chord", frequenciesHz: [220, 261.63, 329.63] },
+  },
+];
+
+const TEST_SIGNAL_PREFIX = "signal:";
 
 export function Controls() {
-  const { startCapture, stopCapture, isCapturing } = useAudioStream();
+  const { startCapture, startGenerator, stopCapture, isCapturing } = useAudioStream();
   const { isRecording, duration, startRecording, stopRecording } = useRecorder();
   const devices = useAudioStore((s) => s.devices);
   const source = useAudioStore((s) => s.source);
@@ -79,13 +100,23 @@ export function Controls() {
     };
   }, []);
 
+  // Starts whatever the selector points at: an input device or a test signal
+  const startSelected = useCallback(() => {
+    if (selectedDevice.startsWith(TEST_SIGNAL_PREFIX)) {
+      const id = selectedDevice.slice(TEST_SIGNAL_PREFIX.length);
+      const preset = TEST_SIGNALS.find((t) => t.id === id);
+      if (preset) return startGenerator(preset.signal);
+    }
+    return startCapture(selectedDevice || undefined);
+  }, [startCapture, startGenerator, selectedDevice]);
+
   // Register capture functions for keyboard shortcuts
   useEffect(() => {
     useAudioStore.getState().setCaptureFns(
-      () => startCapture(selectedDevice || undefined),
+      () => startSelected(),
       () => stopCapture(),
     );
-  }, [startCapture, stopCapture, selectedDevice]);
+  }, [startSelected, stopCapture]);
 
   // Listen for recording toggle from keyboard shortcut
   useEffect(() => {
@@ -142,7 +173,7 @@ export function Controls() {
       return;
     }
 
-    await startCapture(selectedDevice || undefined);
+    await startSelected();
   };
 
   const handleRecord = async () => {
@@ -209,25 +240,33 @@ export function Controls() {
               {isRecording ? `${duration}s` : "Rec"}
             </button>
 
-            {devices.length > 0 && (
-              <select
-                value={selectedDevice}
-                onChange={(e) => {
-                  const value = e.target.value;
-                  setSelectedDevice(value);
+            <select
+              value={selectedDevice}
+              onChange={(e) => {
+                const value = e.target.value;
+                setSelectedDevice(value);
+                // Test signals aren't devices; only remember real inputs
+                if (!value.startsWith(TEST_SIGNAL_PREFIX)) {
                   useSettingsStore.getState().setLastDeviceName(value || null);
-                }}
-                className="bg-white/10 text-white text-sm rounded-lg px-3 py-2 border border-white/10 outline-none"
-              >
-                <option value="">Default Device</option>
-                {devices.map((d) => (
-                  <option key={d.name} value={d.name}>
-                    {d.name}
-                    {d.isDefault ? " (default)" : ""}
+                }
+              }}
+              className="bg-white/10 text-white text-sm rounded-lg px-3 py-2 border border-white/10 outline-none"
+            >
+              <option value="">Default Device</option>
+              {devices.map((d) => (
+                <option key={d.name} value={d.name}>
+                  {d.name}
+                  {d.isDefault ? " (default)" : ""}
+                </option>
+              ))}
+              <optgroup label="Test signals">
+                {TEST_SIGNALS.map((t) => (
+                  <option key={t.id} value={`${TEST_SIGNAL_PREFIX}${t.id}`}>
+                    {t.label}
                   </option>
                 ))}
-              </select>
-            )}
+              </optgroup>
+            </select>
           </div>
 
           {/* Mode Selector */}
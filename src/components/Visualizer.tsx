@@ -65,7 +65,10 @@ export function Visualizer() {
             spectrum: audioState.spectrumData,
             waveform: audioState.waveformData ?? new Float32Array(1024),
             rms: audioState.frame?.rms ?? 0,
-            centroid: audioState.frame?.centroid ?? 0,
+            // Shaders expect centroid as a fraction of Nyquist
+            centroid: audioState.frame?.sampleRate
+              ? audioState.frame.centroid / (audioState.frame.sampleRate / 2)
+              : 0,
             flux: audioState.frame?.flux ?? 0,
             zcr: audioState.frame?.zcr ?? 0,
             beat: audioState.frame?.beat ?? false,
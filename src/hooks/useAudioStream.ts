@@ -3,41 +3,50 @@ import { invoke, Channel } from "@tauri-apps/api/core";
 import { useAudioStore } from "../stores/audioStore";
 import { useSettingsStore } from "../stores/settingsStore";
 import { useToastStore } from "../stores/toastStore";
-import type { AudioConfig, AudioFrame } from "../types/audio";
+import type { AudioConfig, AudioFrame, GeneratorConfig, Signal } from "../types/audio";
+
+function audioConfig(deviceName: string | null): AudioConfig {
+  const settings = useSettingsStore.getState();
+  return {
+    deviceName,
+    fftSize: settings.fftSize,
+    targetFps: settings.targetFps,
+    sensitivity: settings.sensitivity,
+  };
+}
 
 export function useAudioStream() {
   const isCapturing = useAudioStore((s) => s.isCapturing);
   const channelRef = useRef<Channel<AudioFrame> | null>(null);
 
+  const openChannel = useCallback(() => {
+    const channel = new Channel<AudioFrame>();
+    channelRef.current = channel;
+
+    channel.onmessage = (frame: AudioFrame) => {
+      // Sentinel detection: RMS < 0 means device disconnected
+      if (frame.rms < 0) {
+        useAudioStore.getState().setCapturing(false);
+        useAudioStore.getState().setPaused(false);
+        useAudioStore.getState().setSource(null);
+        useToastStore.getState().addToast("error", "Audio device disconnected");
+        channelRef.current = null;
+        return;
+      }
+      useAudioStore.getState().setFrame(frame);
+    };
+
+    return channel;
+  }, []);
+
   const startCapture = useCallback(
     async (deviceName?: string) => {
       if (isCapturing) {
         await invoke("stop_audio").catch(() => {});
       }
 
-      const channel = new Channel<AudioFrame>();
-      channelRef.current = channel;
-
-      channel.onmessage = (frame: AudioFrame) => {
-        // Sentinel detection: RMS < 0 means device disconnected
-        if (frame.rms < 0) {
-          useAudioStore.getState().setCapturing(false);
-          useAudioStore.getState().setPaused(false);
-          useAudioStore.getState().setSource(null);
-          useToastStore.getState().addToast("error", "Audio device disconnected");
-          channelRef.current = null;
-          return;
-        }
-        useAudioStore.getState().setFrame(frame);
-      };
-
-      const settings = useSettingsStore.getState();
-      const config: AudioConfig = {
-        deviceName: deviceName ?? null,
-        fftSize: settings.fftSize,
-        targetFps: settings.targetFps,
-        sensitivity: settings.sensitivity,
-      };
+      const channel = openChannel();
+      const config = audioConfig(deviceName ?? null);
 
       try {
         await invoke("start_audio", { config, channel });
@@ -61,7 +70,30 @@ export function useAudioStream() {
         }
       }
     },
-    [isCapturing],
+    [isCapturing, openChannel],
+  );
+
+  /** Feeds a synthetic test signal through the live pipeline, no device needed. */
+  const startGenerator = useCallback(
+    async (signal: Signal) => {
+      if (isCapturing) {
+        await invoke("stop_audio").catch(() => {});
+      }
+
+      const channel = openChannel();
+      const generator: GeneratorConfig = { signal };
+
+      try {
+        await invoke("start_generator_audio", { generator, config: audioConfig(null), channel });
+        useAudioStore.getState().setCapturing(true);
+        useAudioStore.getState().setPaused(false);
+        useAudioStore.getState().setSource("generator");
+      } catch (err) {
+        channelRef.current = null;
+        useToastStore.getState().addToast("error", `Test signal error: ${String(err)}`);
+      }
+    },
+    [isCapturing, openChannel],
   );
 
   const stopCapture = useCallback(async () => {
@@ -72,5 +104,5 @@ export function useAudioStream() {
     channelRef.current = null;
   }, []);
 
-  return { startCapture, stopCapture, isCapturing };
+  return { startCapture, startGenerator, stopCapture, isCapturing };
 }
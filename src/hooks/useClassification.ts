@@ -1,15 +1,25 @@
 import { useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { useAudioStore } from "../stores/audioStore";
+import type { FeatureSummary } from "../types/audio";
 
 interface FeatureAccumulator {
   rms: number;
   centroid: number;
   flux: number;
   zcr: number;
+  mfcc: number[];
+  chroma: number[];
   count: number;
 }
 
+function addInto(sums: number[], values: number[] | undefined): void {
+  if (!values) return;
+  values.forEach((v, i) => {
+    sums[i] = (sums[i] ?? 0) + v;
+  });
+}
+
 function computeBeatRegularity(beatTimes: number[]): number {
   if (beatTimes.length < 3) return 0.5;
 
@@ -32,7 +42,15 @@ function computeBeatRegularity(beatTimes: number[]): number {
 }
 
 export function useClassification(): void {
-  const accRef = useRef<FeatureAccumulator>({ rms: 0, centroid: 0, flux: 0, zcr: 0, count: 0 });
+  const accRef = useRef<FeatureAccumulator>({
+    rms: 0,
+    centroid: 0,
+    flux: 0,
+    zcr: 0,
+    mfcc: [],
+    chroma: [],
+    count: 0,
+  });
   const beatTimesRef = useRef<number[]>([]);
 
   useEffect(() => {
@@ -50,6 +68,8 @@ export function useClassification(): void {
       acc.centroid += frame.centroid;
       acc.flux += frame.flux;
       acc.zcr += frame.zcr;
+      addInto(acc.mfcc, frame.mfcc);
+      addInto(acc.chroma, frame.chroma);
       acc.count += 1;
 
       if (frame.beat) {
@@ -70,18 +90,25 @@ export function useClassification(): void {
         return;
       }
 
-      const avgRms = acc.rms / acc.count;
-      const avgCentroid = acc.centroid / acc.count;
-      const avgFlux = acc.flux / acc.count;
-      const avgZcr = acc.zcr / acc.count;
-      const bpm = store.frame?.bpm ?? 0;
-      const beatRegularity = computeBeatRegularity(beatTimesRef.current);
+      const summary: FeatureSummary = {
+        avgRms: acc.rms / acc.count,
+        avgCentroid: acc.centroid / acc.count,
+        avgFlux: acc.flux / acc.count,
+        avgZcr: acc.zcr / acc.count,
+        bpm: store.frame?.bpm ?? 0,
+        beatRegularity: computeBeatRegularity(beatTimesRef.current),
+        mfcc: acc.mfcc.map((v) => v / acc.count),
+        chroma: acc.chroma.map((v) => v / acc.count),
+        key: store.frame?.key ?? null,
+      };
 
       // Reset accumulator
       acc.rms = 0;
       acc.centroid = 0;
       acc.flux = 0;
       acc.zcr = 0;
+      acc.mfcc = [];
+      acc.chroma = [];
       acc.count = 0;
 
       store.setIsClassifying(true);
@@ -89,7 +116,7 @@ export function useClassification(): void {
       try {
         const result = await invoke<{ genre: string; mood: string; energy: string }>(
           "classify_audio",
-          { avgRms, avgCentroid, avgFlux, avgZcr, bpm, beatRegularity },
+          { summary },
         );
         useAudioStore.getState().setClassification(result);
       } catch {
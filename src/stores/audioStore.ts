@@ -7,7 +7,7 @@ interface Classification {
   energy: string;
 }
 
-type AudioSource = "live" | "file" | null;
+type AudioSource = "live" | "file" | "generator" | null;
 
 interface AudioState {
   frame: AudioFrame | null;
@@ -56,9 +56,12 @@ export const useAudioStore = create<AudioState>((set) => ({
     set((state) => {
       const spectrum = new Float32Array(frame.spectrum);
 
-      // Temporal smoothing (alpha=0.3: 30% new, 70% old)
+      // Prefer the backend's frame-rate-independent envelope; fall back to
+      // temporal smoothing (alpha=0.3: 30% new, 70% old) without it
       let smoothed: Float32Array;
-      if (state.smoothedSpectrum && state.smoothedSpectrum.length === spectrum.length) {
+      if (frame.smoothed?.length === spectrum.length) {
+        smoothed = new Float32Array(frame.smoothed);
+      } else if (state.smoothedSpectrum && state.smoothedSpectrum.length === spectrum.length) {
         smoothed = state.smoothedSpectrum;
         for (let i = 0; i < spectrum.length; i++) {
           smoothed[i] = smoothed[i] * 0.7 + spectrum[i] * 0.3;
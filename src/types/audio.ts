@@ -1,13 +1,103 @@
 export interface AudioFrame {
   spectrum: number[];
+  smoothed?: number[];
+  peaks?: number[];
   waveform: number[];
   rms: number;
+  /** EBU R128 loudness of the stream so far. */
+  loudness?: Loudness;
   centroid: number;
+  rolloff: number;
+  flatness: number;
+  crest: number;
+  bandwidth: number;
+  slope: number;
+  bandEnergy: BandEnergies;
   flux: number;
   zcr: number;
+  /** Mel-frequency cepstral coefficients; empty unless enabled. */
+  mfcc?: number[];
+  /** Energy per pitch class, C first, loudest at 1; empty unless enabled. */
+  chroma?: number[];
+  /** Key over the last half minute or so; needs `chroma` enabled. */
+  key?: KeyEstimate | null;
+  /** Triad this frame sounds closest to; needs `chroma` enabled. */
+  chord?: Chord | null;
+  /** Fundamental of monophonic input; needs `pitch` enabled. */
+  pitch?: Pitch | null;
+  /** Per-channel spectra and stereo image; absent with `monoDownmix`. */
+  stereo?: StereoResult | null;
   beat: boolean;
   bpm: number;
+  /** How sure the tempo is, 0..1; low means `bpm` is from onset intervals. */
+  tempoConfidence?: number;
+  /** `bpm` is a manual lock and beats come from its clock grid. */
+  bpmLocked?: boolean;
+  /** Kick, snare and hi-hat hits in this frame. */
+  onsets?: Onsets;
+  /** Beat grid, bar and phrase position; null until tracking. */
+  beatPosition?: BeatPosition | null;
   timestamp: number;
+  sampleRate: number;
+  binHz: number;
+}
+
+export interface BandEnergies {
+  sub: number;
+  bass: number;
+  lowMid: number;
+  mid: number;
+  highMid: number;
+  presence: number;
+  brilliance: number;
+}
+
+export interface Loudness {
+  /** LUFS over the last 400 ms. */
+  momentary: number;
+  /** LUFS over the last 3 s. */
+  shortTerm: number;
+  /** Gated LUFS since metering started. */
+  integrated: number;
+  /** Loudness range in LU. */
+  range: number;
+  /** Highest oversampled peak in dBTP. */
+  truePeak: number;
+}
+
+export interface PlaybackFinished {
+  path: string;
+  durationSecs: number;
+  /** Loudness of the track as it played. */
+  loudness?: Loudness | null;
+}
+
+export interface TrackChanged {
+  id: number;
+  index: number | null;
+  path: string;
+  durationSecs: number;
+}
+
+export interface PlaybackPosition {
+  path: string;
+  positionSecs: number;
+  durationSecs: number;
+  finished: boolean;
+}
+
+export type RepeatMode = "off" | "all" | "one";
+
+export interface PlaylistTrack {
+  id: number;
+  path: string;
+}
+
+export interface Playlist {
+  tracks: PlaylistTrack[];
+  currentId: number | null;
+  shuffle: boolean;
+  repeat: RepeatMode;
 }
 
 export interface AudioDevice {
@@ -16,9 +106,190 @@ export interface AudioDevice {
   isInput: boolean;
 }
 
+export type SpectrumLayout = "linear" | "log" | "mel" | "thirdOctave";
+
+export type SpectrumScale = "normalized" | "decibel";
+
 export interface AudioConfig {
   deviceName: string | null;
   fftSize: number;
   targetFps: number;
   sensitivity: number;
+  outputDeviceName?: string | null;
+  hopSize?: number | null;
+  spectrumLayout?: SpectrumLayout;
+  bandCount?: number;
+  spectrumScale?: SpectrumScale;
+  dbFloor?: number;
+  dbCeiling?: number;
+  autoGain?: boolean;
+  attackMs?: number;
+  releaseMs?: number;
+  peakHoldMs?: number;
+  peakFallMs?: number;
+  mfcc?: boolean;
+  chroma?: boolean;
+  pitch?: boolean;
+  monoDownmix?: boolean;
+  onsetBands?: OnsetBands;
+}
+
+export interface OnsetBand {
+  lowHz: number;
+  highHz: number;
+}
+
+export interface OnsetBands {
+  kick: OnsetBand;
+  snare: OnsetBand;
+  hat: OnsetBand;
+}
+
+export interface Onset {
+  detected: boolean;
+  /** How far the hit cleared the band's threshold, 0..1. */
+  strength: number;
+}
+
+export interface BeatPosition {
+  /** Progress from the last beat to the next, 0..1. */
+  phase: number;
+  /** Predicted time of the next beat, on the frame timestamp clock. */
+  nextBeat: number;
+  /** Beats into the bar, 0..beats per bar. */
+  barPosition: number;
+  /** Bars since tracking started, counted at each downbeat. */
+  bar: number;
+  /** Beat within the bar, 0 on the downbeat. */
+  beatInBar: number;
+  /** The bar's first beat ticked in this frame. */
+  downbeat: boolean;
+  timeSignature: TimeSignature;
+  /** Bar within the current 16-bar phrase, 0..16. */
+  barInPhrase: number;
+  /** On the downbeat that starts a phrase: 16 or 8 bars. */
+  phraseBoundary?: number | null;
+}
+
+/** In 6/8 the tracked beats are the eighth notes. */
+export type TimeSignature = "4/4" | "3/4" | "6/8";
+
+export interface Onsets {
+  kick: Onset;
+  snare: Onset;
+  hat: Onset;
+}
+
+export interface SpectrumBand {
+  lowHz: number;
+  centerHz: number;
+  highHz: number;
+}
+
+export interface EnvelopePoint {
+  time: number;
+  min: number;
+  max: number;
+  rms: number;
+}
+
+export interface TempoPoint {
+  time: number;
+  bpm: number;
+}
+
+export interface Section {
+  start: number;
+  end: number;
+  avgRms: number;
+  avgCentroid: number;
+  energy: "low" | "medium" | "high";
+}
+
+export type Mode = "major" | "minor";
+
+export interface KeyEstimate {
+  /** Pitch class of the tonic, 0 = C. */
+  tonic: number;
+  mode: Mode;
+  confidence: number;
+}
+
+export interface Chord {
+  /** Pitch class of the root, 0 = C. */
+  root: number;
+  quality: Mode;
+  confidence: number;
+}
+
+export interface Pitch {
+  /** Fundamental in Hz. */
+  frequency: number;
+  /** MIDI note number of the nearest note, 69 = A4. */
+  midi: number;
+  /** Nearest note, e.g. "A4". */
+  note: string;
+  /** Distance from the nearest note, -50..50. */
+  cents: number;
+  confidence: number;
+}
+
+export interface StereoResult {
+  /** Left channel spectrum, laid out and scaled like `spectrum`. */
+  left: number[];
+  right: number[];
+  /** Phase correlation, -1 (out of phase) to 1 (mono). */
+  correlation: number;
+  /** Energy balance, -1 (all left) to 1 (all right). */
+  balance: number;
+  /** RMS of (L + R) / 2. */
+  mid: number;
+  /** RMS of (L - R) / 2. */
+  side: number;
+  /** Latest samples as [side, mid] points; mono draws a vertical line. */
+  goniometer: [number, number][];
+}
+
+export interface FeatureSummary {
+  avgRms: number;
+  avgCentroid: number;
+  avgFlux: number;
+  avgZcr: number;
+  bpm: number;
+  beatRegularity: number;
+  mfcc?: number[];
+  chroma?: number[];
+  key?: KeyEstimate | null;
+}
+
+export interface OfflineAnalysis {
+  durationSecs: number;
+  sampleRate: number;
+  binHz: number;
+  bands: SpectrumBand[];
+  frameRate: number;
+  frameCount: number;
+  frames: AudioFrame[] | null;
+  envelope: EnvelopePoint[];
+  beats: number[];
+  tempoMap: TempoPoint[];
+  sections: Section[];
+  summary: FeatureSummary;
+  loudness: Loudness;
+}
+
+/** Test signal for the generator source. */
+export type Signal =
+  | { kind: "sine"; frequencyHz: number }
+  /** Exponential sweep, starting over every `durationSecs`. */
+  | { kind: "sweep"; startHz: number; endHz: number; durationSecs: number }
+  | { kind: "whiteNoise" }
+  | { kind: "pinkNoise" }
+  | { kind: "clicks"; bpm: number }
+  | { kind: "chord"; frequenciesHz: number[] };
+
+export interface GeneratorConfig {
+  signal: Signal;
+  /** Peak level, 0..1. */
+  amplitude?: number;
 }